@@ -0,0 +1,271 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    process::Command,
+    sync::{Condvar, Mutex},
+    time::Duration
+};
+use curl::easy::{Easy, List};
+use crate::tui;
+
+// Number of 401 responses in a row, with nothing accepted in between, before
+// the token is suspected to have expired
+// This doubles each time the token turns out to be fine, so that paths
+// which always respond with 401 don't cause a check every few requests
+const EXPIRY_REJECTIONS: u32 = 3;
+
+// Where a new token is acquired from when requests start returning 401
+pub enum TokenRefresh {
+    // A shell command which prints the token
+    Command(String),
+    // An endpoint which responds with the token, requested with a POST of
+    // the form data if given, otherwise with a GET
+    Url(String, Option<String>)
+}
+
+struct TokenState {
+    token: String,
+    // Incremented each time the token is replaced, so that threads which
+    // saw a 401 with an old token just pick up the new one
+    generation: u64,
+    // A url which has responded with something other than 401, used to
+    // tell an expired token apart from paths which always need more access
+    accepted_url: Option<String>,
+    // 401 responses received since the last response which wasn't one, and
+    // the number at which the token is checked
+    rejections: u32,
+    rejection_limit: u32,
+    // Set while a thread is acquiring a new token without holding the lock
+    refreshing: bool,
+    // Refreshing stops after the first failure rather than retrying on
+    // every 401 for the rest of the scan
+    failed: bool
+}
+
+// The bearer token sent with every request, shared between the threads
+pub struct BearerToken {
+    state: Mutex<TokenState>,
+    refreshed: Condvar,
+    refresh: Option<TokenRefresh>
+}
+
+impl BearerToken {
+    // Creates the token, acquiring the first one from the refresh source if
+    // no token was given
+    pub fn new(token: Option<String>, refresh: Option<TokenRefresh>) -> Result<BearerToken, String> {
+        let token = match (token, &refresh) {
+            (Some(token), _) => token,
+            (None, Some(refresh)) => acquire(refresh)?,
+            (None, None) => return Err(String::from("no token or way to acquire one was given"))
+        };
+
+        Ok(BearerToken {
+            state: Mutex::new(TokenState {
+                token,
+                generation: 0,
+                accepted_url: None,
+                rejections: 0,
+                rejection_limit: EXPIRY_REJECTIONS,
+                refreshing: false,
+                failed: false
+            }),
+            refreshed: Condvar::new(),
+            refresh
+        })
+    }
+
+    // Returns the current token along with its generation
+    pub fn current(&self) -> (String, u64) {
+        let state = self.state.lock().unwrap();
+        (state.token.clone(), state.generation)
+    }
+
+    // Records a response to a request made with the token which wasn't a 401
+    pub fn accepted(&self, url: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.rejections = 0;
+        if state.accepted_url.is_none() {
+            state.accepted_url = Some(String::from(url));
+        }
+    }
+
+    // Records a 401 response to a request made with the given generation
+    // Once enough have been received in a row, returns a url which accepted
+    // the token before to check whether the token itself is now rejected
+    pub fn rejected(&self, generation: u64) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        if self.refresh.is_none() || state.failed || state.generation != generation {
+            return None
+        }
+        state.rejections += 1;
+        if state.rejections < state.rejection_limit { return None }
+        state.rejections = 0;
+        state.accepted_url.clone()
+    }
+
+    // Records that the url returned by rejected still accepts the token
+    pub fn still_accepted(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.rejection_limit = state.rejection_limit.saturating_mul(2);
+    }
+
+    // Replaces the token after a url which accepted it has rejected a request
+    // made with the given generation, returning true if there is a newer
+    // token to retry with
+    // The token is acquired without holding the lock, other threads which
+    // need it wait for the result
+    pub fn refresh(&self, generation: u64) -> bool {
+        let mut state = self.state.lock().unwrap();
+        while state.refreshing {
+            state = self.refreshed.wait(state).unwrap();
+        }
+        if state.generation != generation {
+            return true
+        }
+        let refresh = match &self.refresh {
+            Some(refresh) if !state.failed => refresh,
+            _ => return false
+        };
+        state.refreshing = true;
+        drop(state);
+
+        let result = acquire(refresh);
+
+        let mut state = self.state.lock().unwrap();
+        state.refreshing = false;
+        state.rejections = 0;
+        self.refreshed.notify_all();
+        match result {
+            Ok(token) => {
+                tui::message(String::from("Refreshed the bearer token after receiving a 401"));
+                state.token = token;
+                state.generation += 1;
+                state.rejection_limit = EXPIRY_REJECTIONS;
+                true
+            },
+            Err(e) => {
+                tui::message(format!("Couldn't refresh the bearer token, continuing with the old one: {}", e));
+                state.failed = true;
+                false
+            }
+        }
+    }
+}
+
+fn acquire(refresh: &TokenRefresh) -> Result<String, String> {
+    let output = match refresh {
+        TokenRefresh::Command(command) => run_command(command)?,
+        TokenRefresh::Url(url, form_data) => fetch(url, form_data.as_deref())?
+    };
+    parse_token(&output)
+}
+
+fn run_command(command: &str) -> Result<String, String> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    }
+    else {
+        Command::new("sh").args(["-c", command]).output()
+    }.map_err(|e| format!("couldn't run {}: {}", command, e))?;
+
+    if !output.status.success() {
+        return Err(format!("{} exited with {}", command, output.status))
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn fetch(url: &str, form_data: Option<&str>) -> Result<String, String> {
+    let mut easy = Easy::new();
+    let mut contents = Vec::new();
+    easy.url(url).map_err(|e| e.to_string())?;
+    easy.timeout(Duration::from_secs(30)).unwrap();
+    if let Some(form_data) = form_data {
+        easy.post_fields_copy(form_data.as_bytes()).unwrap();
+        let mut headers = List::new();
+        headers.append("Content-Type: application/x-www-form-urlencoded").unwrap();
+        easy.http_headers(headers).unwrap();
+    }
+
+    {
+        let mut transfer = easy.transfer();
+        transfer.write_function(|data| {
+            contents.extend_from_slice(data);
+            Ok(data.len())
+        }).unwrap();
+        transfer.perform().map_err(|e| e.to_string())?;
+    }
+
+    match easy.response_code().unwrap() {
+        200 => Ok(String::from_utf8_lossy(&contents).to_string()),
+        code => Err(format!("{} responded with {}", url, code))
+    }
+}
+
+// Takes the access_token from an OAuth style JSON response, otherwise the
+// output is expected to be just the token
+fn parse_token(output: &str) -> Result<String, String> {
+    let token = match serde_json::from_str::<serde_json::Value>(output) {
+        Ok(json) => match json.get("access_token").and_then(|token| token.as_str()) {
+            Some(token) => String::from(token),
+            None => return Err(String::from("the JSON response doesn't contain an access_token"))
+        },
+        Err(_) => String::from(output.trim())
+    };
+
+    if token.is_empty() || token.chars().any(char::is_whitespace) {
+        return Err(String::from("the output isn't a token"))
+    }
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_parse_token() {
+        assert_eq!(super::parse_token("abc.def.ghi\n"), Ok(String::from("abc.def.ghi")));
+        assert_eq!(super::parse_token(r#"{"access_token": "xyz", "expires_in": 3600}"#),
+            Ok(String::from("xyz")));
+        assert!(super::parse_token(r#"{"error": "invalid_client"}"#).is_err());
+        assert!(super::parse_token("not a token").is_err());
+        assert!(super::parse_token("").is_err());
+    }
+
+    #[test]
+    fn check_expiry_detection() {
+        let token = super::BearerToken::new(Some("abc".into()),
+            Some(super::TokenRefresh::Command("echo def".into()))).unwrap();
+
+        // Nothing has accepted the token, so there's nothing to check it with
+        for _ in 0..super::EXPIRY_REJECTIONS { assert_eq!(token.rejected(0), None) }
+
+        token.accepted("http://example.com/");
+        assert_eq!(token.rejected(0), None);
+        assert_eq!(token.rejected(0), None);
+        token.accepted("http://example.com/other");
+        for _ in 1..super::EXPIRY_REJECTIONS { assert_eq!(token.rejected(0), None) }
+        assert_eq!(token.rejected(0), Some(String::from("http://example.com/")));
+
+        // Twice as many are needed once the token has been found to still work
+        token.still_accepted();
+        for _ in 1..super::EXPIRY_REJECTIONS * 2 { assert_eq!(token.rejected(0), None) }
+        assert_eq!(token.rejected(0), Some(String::from("http://example.com/")));
+
+        // Rejections of an old token are picked up by the refresh instead
+        assert_eq!(token.rejected(1), None);
+    }
+}
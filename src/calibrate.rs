@@ -0,0 +1,324 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex}
+};
+use curl::easy::Easy2;
+use rand::{thread_rng, Rng, distributions::Alphanumeric};
+use crate::arg_parse::GlobalOpts;
+use crate::content_parse;
+use crate::request::{self, Collector, RequestResponse};
+use crate::tui;
+use crate::wordlist::UriGenerator;
+
+// Lengths of the random words the directory is probed with
+const PROBE_LENGTHS: [usize; 2] = [8, 16];
+
+// Percentage a response's size can differ from an --exclude-like url's and
+// still be taken to be the same page
+const EXCLUDE_LIKE_TOLERANCE: usize = 5;
+
+// Number of recent requests the hit rate of a directory is measured over
+const DRIFT_WINDOW: usize = 20;
+// Number of those requests which have to be reported for the directory to
+// be recalibrated
+const DRIFT_HITS: usize = 18;
+
+// One way the directory responds to a path which doesn't exist
+struct Signature {
+    code: u32,
+    is_directory: bool,
+    // Whether the probe's url ended with a slash
+    slash: bool,
+    size: i64,
+    url_len: i64,
+    simhash: u64
+}
+
+impl Signature {
+    fn from(response: &RequestResponse) -> Signature {
+        Signature {
+            code: response.code,
+            is_directory: response.is_directory,
+            slash: response.url.ends_with('/'),
+            size: response.content_len as i64,
+            url_len: response.url.len() as i64,
+            simhash: response.body_simhash
+        }
+    }
+}
+
+// The responses a directory gives for paths which don't exist, built from
+// several random probes so that results from servers which don't respond
+// with 404 for missing pages can be told apart from real findings
+// Sizes are allowed to change with the length of the url, so a not found
+// page which includes the requested path in its body still matches
+pub struct NotFound {
+    signatures: Vec<Signature>,
+    // Change in size for each extra character in the url
+    size_per_char: i64,
+    // The --similarity threshold, responses with a body this similar to a
+    // probe's match it whatever their size
+    similarity: Option<u32>
+}
+
+impl NotFound {
+    // Probes the generator's directory with random words of two lengths,
+    // then with a word without the generator's extension and a word with a
+    // trailing slash in case they get a different not found page
+    // Returns None if the two lengths don't respond the same way
+    pub fn measure(easy: &mut Easy2<Collector>, uri_gen: &UriGenerator, similarity: Option<u32>) -> Option<NotFound> {
+        let probes: Vec<RequestResponse> = PROBE_LENGTHS.iter()
+            .map(|length| request::make_request(easy, uri_gen.uri_for(&random_word(*length))))
+            .collect();
+        let (first, second) = (&probes[0], &probes[1]);
+        if first.code == 0 || first.code != second.code || first.is_directory != second.is_directory {
+            return None
+        }
+
+        let url_difference = second.url.len() as i64 - first.url.len() as i64;
+        let mut not_found = NotFound {
+            signatures: vec![Signature::from(first)],
+            size_per_char: (second.content_len as i64 - first.content_len as i64) / url_difference,
+            similarity
+        };
+
+        let word = random_word(PROBE_LENGTHS[0]);
+        let other_urls = vec![Some(uri_gen.uri_for(&word) + "/"), uri_gen.uri_without_suffix(&word)];
+        for url in other_urls.into_iter().flatten() {
+            let response = request::make_request(easy, url);
+            if response.code != 0 && !not_found.matches(&response) {
+                not_found.signatures.push(Signature::from(&response));
+            }
+        }
+
+        Some(not_found)
+    }
+
+    // Whether the response is the same as one of the directory's responses
+    // for a path which doesn't exist
+    // Directory listings are only compared by code and body, as a url with
+    // a trailing slash is always taken to be a directory
+    pub fn matches(&self, response: &RequestResponse) -> bool {
+        let slash = response.url.ends_with('/');
+        self.signatures.iter().any(|signature| {
+            let expected_size = signature.size + self.size_per_char * (response.url.len() as i64 - signature.url_len);
+            response.code == signature.code && slash == signature.slash
+                && (slash || response.is_directory == signature.is_directory)
+                && (response.content_len as i64 == expected_size
+                    || self.similarity.is_some_and(|similarity|
+                        content_parse::similar(response.body_simhash, signature.simhash, similarity)))
+        })
+    }
+}
+
+// Watches for a calibrated directory suddenly reporting nearly every path,
+// as happens when a WAF or load balancer changes how it responds partway
+// through a scan
+// Results are held back until they leave the window, so that the ones
+// which turn out to be the new not found response can be dropped
+#[derive(Default)]
+pub struct DriftMonitor {
+    // Whether each of the recent requests was reported
+    hits: VecDeque<bool>,
+    // Results still in the window along with their bodies if saved
+    pending: VecDeque<(usize, RequestResponse, Option<Vec<u8>>)>,
+    requests: usize
+}
+
+impl DriftMonitor {
+    pub fn new() -> DriftMonitor {
+        DriftMonitor::default()
+    }
+
+    // Records a request and the result it found if any, returning the
+    // results which have left the window and can be reported
+    pub fn add(&mut self, result: Option<(RequestResponse, Option<Vec<u8>>)>)
+        -> Vec<(RequestResponse, Option<Vec<u8>>)> {
+        self.requests += 1;
+        self.hits.push_back(result.is_some());
+        if self.hits.len() > DRIFT_WINDOW {
+            self.hits.pop_front();
+        }
+        if let Some((response, body)) = result {
+            self.pending.push_back((self.requests, response, body));
+        }
+
+        let mut ready = Vec::new();
+        while self.pending.front().is_some_and(|(request, _, _)| request + DRIFT_WINDOW <= self.requests) {
+            let (_, response, body) = self.pending.pop_front().unwrap();
+            ready.push((response, body));
+        }
+        ready
+    }
+
+    // Whether nearly every request in a full window was reported
+    pub fn drifted(&self) -> bool {
+        self.hits.iter().filter(|hit| **hit).count() >= DRIFT_HITS
+    }
+
+    // Drops the held back results which match the directory's new not found
+    // response and starts a new window, returning the number dropped
+    pub fn recalibrate(&mut self, calibration: Option<&NotFound>) -> usize {
+        self.hits.clear();
+        let held = self.pending.len();
+        if let Some(calibration) = calibration {
+            self.pending.retain(|(_, response, _)| !calibration.matches(response));
+        }
+        held - self.pending.len()
+    }
+
+    // Returns every result still held back, for when the scan of the
+    // directory ends
+    pub fn flush(&mut self) -> Vec<(RequestResponse, Option<Vec<u8>>)> {
+        self.pending.drain(..).map(|(_, response, body)| (response, body)).collect()
+    }
+}
+
+// The pages given with --exclude-like, fetched once before the scan, such
+// as a single sign-on redirect or a catch-all page
+#[derive(Default)]
+pub struct ExcludeLike {
+    // The code, size, redirect destination without its query and body
+    // simhash of each
+    responses: Mutex<Vec<(u32, usize, String, u64)>>
+}
+
+impl ExcludeLike {
+    pub fn new() -> ExcludeLike {
+        ExcludeLike::default()
+    }
+
+    pub fn fetch(&self, urls: &[String], global_opts: Arc<GlobalOpts>) {
+        let mut easy = request::generate_easy(global_opts);
+        for url in urls {
+            let response = request::make_request(&mut easy, url.clone());
+            if response.code == 0 {
+                tui::message(format!("Couldn't fetch {}, results like it won't be excluded", url));
+                continue
+            }
+            self.responses.lock().unwrap().push(
+                (response.code, response.content_len, redirect_target(&response), response.body_simhash));
+        }
+    }
+
+    // Whether the response is substantially the same as one of the pages
+    // Redirects only need to go to the same place, as their bodies often
+    // include the requested path
+    // Other pages are compared by body when a similarity is given, otherwise
+    // by size
+    pub fn matches(&self, response: &RequestResponse, similarity: Option<u32>) -> bool {
+        let redirect = redirect_target(response);
+        self.responses.lock().unwrap().iter().any(|(code, size, excluded_redirect, simhash)| {
+            let same_page = match similarity {
+                Some(similarity) => content_parse::similar(response.body_simhash, *simhash, similarity),
+                None => size.abs_diff(response.content_len) * 100 <= size * EXCLUDE_LIKE_TOLERANCE
+            };
+            *code == response.code && *excluded_redirect == redirect && (!redirect.is_empty() || same_page)
+        })
+    }
+}
+
+// The redirect destination without its query string, which often holds
+// the url that was requested
+fn redirect_target(response: &RequestResponse) -> String {
+    let redirect = &response.redirect_url;
+    String::from(&redirect[..redirect.find(['?', '#']).unwrap_or(redirect.len())])
+}
+
+fn random_word(length: usize) -> String {
+    thread_rng().sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::request::RequestResponse;
+
+    fn response(url: &str, code: u32, content_len: usize) -> RequestResponse {
+        RequestResponse { url: url.into(), code, content_len, ..Default::default() }
+    }
+
+    #[test]
+    fn check_matches() {
+        let not_found = super::NotFound {
+            signatures: vec![
+                super::Signature::from(&response("http://example.com/abcdefgh", 200, 30)),
+                super::Signature::from(&response("http://example.com/abcdefgh/", 200, 12))
+            ],
+            size_per_char: 1,
+            similarity: None
+        };
+
+        // The not found page includes the path, so is longer for longer urls
+        assert!(not_found.matches(&response("http://example.com/login", 200, 27)));
+        assert!(!not_found.matches(&response("http://example.com/login", 200, 30)));
+        assert!(!not_found.matches(&response("http://example.com/login", 403, 27)));
+        assert!(not_found.matches(&response("http://example.com/admin/", 200, 9)));
+    }
+
+    #[test]
+    fn check_drift_monitor() {
+        let mut drift = super::DriftMonitor::new();
+        for _ in 0..super::DRIFT_WINDOW - super::DRIFT_HITS {
+            assert!(drift.add(None).is_empty());
+        }
+        assert!(drift.add(Some((response("http://example.com/login", 200, 27), None))).is_empty());
+        for _ in 1..super::DRIFT_HITS {
+            assert!(!drift.drifted());
+            drift.add(Some((response("http://example.com/abcdefgh", 200, 30), None)));
+        }
+        assert!(drift.drifted());
+
+        let calibration = super::NotFound {
+            signatures: vec![super::Signature::from(&response("http://example.com/abcdefgh", 200, 30))],
+            size_per_char: 0,
+            similarity: None
+        };
+        assert_eq!(drift.recalibrate(Some(&calibration)), super::DRIFT_HITS - 1);
+        assert!(!drift.drifted());
+
+        // The remaining result is reported once it leaves the window
+        for _ in 0..super::DRIFT_WINDOW - super::DRIFT_HITS {
+            assert!(drift.add(None).is_empty());
+        }
+        assert_eq!(drift.add(None).len(), 1);
+        assert!(drift.flush().is_empty());
+    }
+
+    #[test]
+    fn check_exclude_like() {
+        let exclude_like = super::ExcludeLike::new();
+        exclude_like.responses.lock().unwrap().push((302, 120, "https://sso.example.com/login".into(), 0));
+        exclude_like.responses.lock().unwrap().push((200, 1000, String::new(), 0));
+
+        let mut redirect = response("http://example.com/admin", 302, 140);
+        redirect.redirect_url = "https://sso.example.com/login?return=/admin".into();
+        assert!(exclude_like.matches(&redirect, None));
+        redirect.redirect_url = "https://sso.example.com/logout".into();
+        assert!(!exclude_like.matches(&redirect, None));
+
+        assert!(exclude_like.matches(&response("http://example.com/about", 200, 1040), None));
+        assert!(!exclude_like.matches(&response("http://example.com/about", 200, 1200), None));
+    }
+}
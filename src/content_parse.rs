@@ -16,6 +16,7 @@
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
 extern crate select;
+use serde_json::Value;
 use select::document::Document;
 use select::predicate::Name;
 
@@ -73,4 +74,272 @@ pub fn scrape_urls(content: String, original_url: String) -> Vec<String>
     }
 
     output_urls
+}
+
+// Estimates the total size in bytes of the files in a directory listing
+// from the size column shown next to each link, returns None if the
+// listing doesn't include any sizes
+pub fn listing_size(content: &str) -> Option<u64>
+{
+    let mut total_size = 0;
+    let mut found_size = false;
+
+    for line in content.lines() {
+        let link_start = match line.find("<a ") {
+            Some(index) => index,
+            None => continue
+        };
+
+        // Sizes generally follow the link, though some servers put them before it
+        let after_link = match line[link_start..].find("</a>") {
+            Some(index) => strip_tags(&line[link_start+index..]),
+            None => String::new()
+        };
+        let before_link = strip_tags(&line[..link_start]);
+
+        let size = last_size(&after_link).or_else(|| last_size(&before_link));
+        if let Some(size) = size {
+            total_size += size;
+            found_size = true;
+        }
+    }
+
+    if found_size { Some(total_size) } else { None }
+}
+
+// Returns the last whitespace separated token in the text which looks like a size
+fn last_size(text: &str) -> Option<u64>
+{
+    text.split_whitespace().rev().filter_map(parse_size).next()
+}
+
+// Parses sizes such as "12345", "1.2K" or "9G" into a number of bytes
+fn parse_size(token: &str) -> Option<u64>
+{
+    let token = token.to_lowercase();
+    let (number, multiplier) = match token.chars().last() {
+        Some('k') => (&token[..token.len()-1], 1u64 << 10),
+        Some('m') => (&token[..token.len()-1], 1u64 << 20),
+        Some('g') => (&token[..token.len()-1], 1u64 << 30),
+        Some('t') => (&token[..token.len()-1], 1u64 << 40),
+        _ => (&token[..], 1)
+    };
+
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit() || c == '.') {
+        return None
+    }
+
+    number.parse::<f64>().ok().map(|size| (size * multiplier as f64) as u64)
+}
+
+// Text which identifies the error page of a framework, with the text which
+// precedes the framework's version on the page if it is shown
+const FRAMEWORK_SIGNATURES: [(&str, &str, Option<&str>); 8] = [
+    ("Django", "DEBUG = True</code>", Some("Django Version:")),
+    ("ASP.NET", "Server Error in '", Some("ASP.NET Version:")),
+    ("Laravel", "Whoops, looks like something went wrong.", None),
+    ("Laravel", "Illuminate\\", None),
+    ("PHP Whoops", "Whoops! There was an error.", None),
+    ("Spring Boot", "Whitelabel Error Page", None),
+    ("Werkzeug", "Werkzeug powered traceback interpreter", None),
+    ("Ruby on Rails", "Action Controller: Exception caught", None)
+];
+
+// Longest page title shown, longer titles are cut short
+const MAX_TITLE_LENGTH: usize = 100;
+
+// Returns the text of the page's <title> with its whitespace collapsed, or
+// None if it has no title or an empty one
+pub fn page_title(content: &str) -> Option<String>
+{
+    let document = Document::from(content);
+    let title = document.find(Name("title")).next()?.text();
+    let title = title.split_whitespace().collect::<Vec<&str>>().join(" ");
+    if title.is_empty() { return None }
+
+    if title.chars().count() > MAX_TITLE_LENGTH {
+        Some(title.chars().take(MAX_TITLE_LENGTH).collect::<String>() + "...")
+    }
+    else {
+        Some(title)
+    }
+}
+
+// Generates a 64 bit simhash of the words in the content, which changes in
+// only a few bits when a few words change, so that pages differing only by
+// a timestamp or CSRF token can still be recognised as the same
+pub fn simhash(content: &[u8]) -> u64
+{
+    let mut weights = [0i64; 64];
+    for word in content.split(|byte| !byte.is_ascii_alphanumeric()).filter(|word| !word.is_empty()) {
+        let hash = fnv_hash(word);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+
+    weights.iter().enumerate()
+        .filter(|(_, weight)| **weight > 0)
+        .fold(0, |simhash, (bit, _)| simhash | 1 << bit)
+}
+
+// Whether two simhashes agree in at least the given percentage of their bits
+pub fn similar(first: u64, second: u64, threshold: u32) -> bool
+{
+    (64 - (first ^ second).count_ones()) * 100 >= threshold * 64
+}
+
+// FNV-1a hash of a word, ignoring case
+fn fnv_hash(word: &[u8]) -> u64
+{
+    word.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte.to_ascii_lowercase() as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+// Identifies the framework, and its version where shown, from the content
+// of an error page
+pub fn error_framework(content: &str) -> Option<String>
+{
+    let (name, _, version_prefix) = FRAMEWORK_SIGNATURES.iter()
+        .find(|(_, signature, _)| content.contains(signature))?;
+
+    match version_prefix.and_then(|prefix| version_after(content, prefix)) {
+        Some(version) => Some(format!("{} {}", name, version)),
+        None => Some(name.to_string())
+    }
+}
+
+// Returns the paths and urls given as values in a JSON document, such as
+// the "path" or "_links" of an API error, or nothing if it isn't JSON
+pub fn json_endpoint_hints(content: &str) -> Vec<String>
+{
+    let mut hints = Vec::new();
+    if let Ok(document) = serde_json::from_str::<Value>(content) {
+        collect_endpoint_hints(&document, &mut hints);
+    }
+    hints.sort();
+    hints.dedup();
+    hints
+}
+
+fn collect_endpoint_hints(value: &Value, hints: &mut Vec<String>)
+{
+    match value {
+        Value::String(text) => {
+            let looks_like_path = (text.starts_with('/') && !text.starts_with("//") && text.len() > 1)
+                || text.starts_with("http://") || text.starts_with("https://");
+            if looks_like_path && !text.contains(char::is_whitespace) {
+                hints.push(text.clone());
+            }
+        },
+        Value::Array(values) => values.iter().for_each(|value| collect_endpoint_hints(value, hints)),
+        Value::Object(fields) => fields.values().for_each(|value| collect_endpoint_hints(value, hints)),
+        _ => {}
+    }
+}
+
+// Returns the version number following the prefix, skipping any html tags
+// and separators in between, e.g. "2.2.4" from "Version:</th><td>2.2.4</td>"
+fn version_after(content: &str, prefix: &str) -> Option<String>
+{
+    let start = content.find(prefix)? + prefix.len();
+    let mut rest = content[start..].trim_start_matches(|c: char| c == ':' || c == '"' || c.is_whitespace());
+    while rest.starts_with('<') {
+        rest = rest[rest.find('>')? + 1..].trim_start();
+    }
+
+    let version: String = rest.chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '.' || *c == '-')
+        .collect();
+    if version.starts_with(|c: char| c.is_ascii_digit()) { Some(version) } else { None }
+}
+
+// Removes any html tags from the given text
+fn strip_tags(text: &str) -> String
+{
+    let mut output = String::new();
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => { in_tag = false; output.push(' '); },
+            _ if !in_tag => output.push(c),
+            _ => {}
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_listing_size() {
+        let apache = "<tr><td><a href=\"?c=n;o=d\">name</a></td><td>size</td></tr>
+<tr><td><a href=\"/\">parent directory</a></td><td>&nbsp;</td><td align=\"right\">  - </td></tr>
+<tr><td><a href=\"a.zip\">a.zip</a></td><td align=\"right\">2019-04-01 12:00  </td><td align=\"right\">1.5k</td><td>&nbsp;</td></tr>
+<tr><td><a href=\"b.iso\">b.iso</a></td><td align=\"right\">2019-04-01 12:00  </td><td align=\"right\">2g</td><td>&nbsp;</td></tr>";
+        assert_eq!(super::listing_size(apache), Some(1536 + (2 << 30)));
+
+        let nginx = "<a href=\"../\">../</a>
+<a href=\"a.txt\">a.txt</a>                                              01-apr-2019 12:00                 100
+<a href=\"b.txt\">b.txt</a>                                              01-apr-2019 12:00                 23";
+        assert_eq!(super::listing_size(nginx), Some(123));
+
+        let no_sizes = "<li><a href=\"a.txt\">a.txt</a></li>";
+        assert_eq!(super::listing_size(no_sizes), None);
+    }
+
+    #[test]
+    fn check_error_framework() {
+        let django = "<p>You're seeing this error because you have <code>DEBUG = True</code></p>
+<tr>\n  <th>Django Version:</th>\n  <td>2.2.4</td>\n</tr>";
+        assert_eq!(super::error_framework(django), Some(String::from("Django 2.2.4")));
+
+        let aspnet = "<h1>Server Error in '/' Application.</h1><b>Version Information:</b>&nbsp;\
+Microsoft .NET Framework Version:4.0.30319; ASP.NET Version:4.7.3062.0";
+        assert_eq!(super::error_framework(aspnet), Some(String::from("ASP.NET 4.7.3062.0")));
+
+        let spring = "<h1>Whitelabel Error Page</h1>";
+        assert_eq!(super::error_framework(spring), Some(String::from("Spring Boot")));
+
+        assert_eq!(super::error_framework("<h1>Internal Server Error</h1>"), None);
+    }
+
+    #[test]
+    fn check_page_title() {
+        assert_eq!(super::page_title("<html><head><title>\n  Index of /files\n</title></head></html>"),
+            Some(String::from("Index of /files")));
+        assert_eq!(super::page_title("<title>Tom &amp; Jerry</title>"), Some(String::from("Tom & Jerry")));
+        assert_eq!(super::page_title("<title> </title>"), None);
+        assert_eq!(super::page_title("<h1>No title</h1>"), None);
+    }
+
+    #[test]
+    fn check_simhash() {
+        let page = |token: &str| format!("<html><head><title>Sign in</title></head><body><form method=\"post\">\
+            <input type=\"hidden\" name=\"csrf\" value=\"{}\"><label>Username</label><input name=\"user\">\
+            <label>Password</label><input name=\"pass\" type=\"password\"><button>Sign in</button></form>\
+            <p>Forgotten your password? Contact the help desk</p></body></html>", token);
+        let first = super::simhash(page("a1b2c3d4e5").as_bytes());
+        let second = super::simhash(page("f6g7h8i9j0").as_bytes());
+        assert!(super::similar(first, second, 90));
+
+        let other = super::simhash(b"<html><body><h1>Quarterly report</h1><table><tr><td>Revenue</td>\
+            <td>1,200,000</td></tr><tr><td>Costs</td><td>950,000</td></tr></table></body></html>");
+        assert!(!super::similar(first, other, 90));
+        assert!(super::similar(first, first, 100));
+    }
+
+    #[test]
+    fn check_json_endpoint_hints() {
+        let error = r#"{"status": 405, "error": "Method Not Allowed", "path": "/api/v2/users",
+            "_links": {"docs": {"href": "https://example.com/api/docs"}}, "message": "Use / for the index"}"#;
+        assert_eq!(super::json_endpoint_hints(error),
+            vec!["/api/v2/users".to_string(), "https://example.com/api/docs".to_string()]);
+
+        assert!(super::json_endpoint_hints("<h1>/not/json</h1>").is_empty());
+    }
 }
\ No newline at end of file
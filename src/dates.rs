@@ -0,0 +1,113 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Number of days before today covered when no --date-range is given
+pub const DEFAULT_RANGE_DAYS: i64 = 365;
+
+// Converts a count of days since the unix epoch to a year, month and day
+pub fn civil_from_days(days: i64) -> (i64, i64, i64)
+{
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+// Converts a year, month and day to a count of days since the unix epoch
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64
+{
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// Returns today's date as days since the unix epoch, in UTC
+pub fn today() -> i64
+{
+    (SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() / 86400) as i64
+}
+
+// Parses a YYYY-MM-DD date into days since the unix epoch
+pub fn parse_date(value: &str) -> Option<i64>
+{
+    let mut parts = value.trim().splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    let days = days_from_civil(year, month, day);
+    // Dates such as the 31st of February don't survive the round trip
+    if civil_from_days(days) == (year, month, day) { Some(days) } else { None }
+}
+
+// Parses a range of dates given as start:end
+pub fn parse_date_range(value: &str) -> Result<(i64, i64), String>
+{
+    let dates = value.split_once(':').and_then(|(start, end)| Some((parse_date(start)?, parse_date(end)?)));
+    match dates {
+        Some((start, end)) if start <= end => Ok((start, end)),
+        _ => Err(format!("{} isn't a range of dates such as 2024-01-01:2024-12-31", value))
+    }
+}
+
+// Generates the words for each day in the range from each of the patterns,
+// replacing {YYYY}, {YY}, {MM}, {M}, {DD} and {D} with the date's parts
+// Patterns without a day give the same word for each day of the month, so
+// the words are deduplicated
+pub fn date_words(patterns: &[String], (start, end): (i64, i64)) -> Vec<String>
+{
+    let mut words: Vec<String> = (start..=end)
+        .flat_map(|days| {
+            let (year, month, day) = civil_from_days(days);
+            patterns.iter().map(move |pattern| pattern
+                .replace("{YYYY}", &format!("{:04}", year))
+                .replace("{YY}", &format!("{:02}", year % 100))
+                .replace("{MM}", &format!("{:02}", month))
+                .replace("{M}", &month.to_string())
+                .replace("{DD}", &format!("{:02}", day))
+                .replace("{D}", &day.to_string()))
+        })
+        .collect();
+    words.sort();
+    words.dedup();
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_date_words() {
+        assert_eq!(super::parse_date("2024-02-29"), Some(super::days_from_civil(2024, 2, 29)));
+        assert_eq!(super::parse_date("2023-02-29"), None);
+        assert_eq!(super::civil_from_days(super::parse_date("1999-12-31").unwrap()), (1999, 12, 31));
+        assert!(super::parse_date_range("2024-02-01:2024-01-01").is_err());
+
+        let range = super::parse_date_range("2023-12-31:2024-01-02").unwrap();
+        assert_eq!(super::date_words(&["backup-{YYYY}{MM}{DD}.zip".into()], range),
+            vec!["backup-20231231.zip", "backup-20240101.zip", "backup-20240102.zip"]);
+        assert_eq!(super::date_words(&["{YY}-{M}".into(), "{D}".into()], range),
+            vec!["1", "2", "23-12", "24-1", "31"]);
+    }
+}
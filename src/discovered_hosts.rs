@@ -0,0 +1,147 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    sync::Arc
+};
+use regex::Regex;
+use crate::arg_parse::GlobalOpts;
+use crate::request::{self, RequestResponse};
+use crate::url_parse;
+
+// Hostnames seen during the scan which look like they belong to the target,
+// from certificates, redirects and response bodies
+pub struct DiscoveredHosts {
+    domains: BTreeSet<String>,
+    scanned: BTreeSet<String>,
+    // The url each host was first seen with, keyed by hostname
+    found: BTreeMap<String, String>
+}
+
+impl DiscoveredHosts {
+    pub fn new(global_opts: &GlobalOpts) -> DiscoveredHosts {
+        DiscoveredHosts {
+            domains: target_domains(global_opts).into_iter().collect(),
+            scanned: global_opts.hostnames.iter()
+                .map(|hostname| url_parse::host(hostname).to_lowercase())
+                .collect(),
+            found: BTreeMap::new()
+        }
+    }
+
+    // Records the hosts a response redirected to or mentioned in its body,
+    // returning the url of each which hadn't been seen before
+    pub fn add_response(&mut self, response: &RequestResponse, global_opts: &GlobalOpts) -> Vec<String> {
+        let mut urls = Vec::new();
+        if !response.redirect_url.is_empty() {
+            urls.push(url_parse::base_url(&response.redirect_url));
+        }
+        let scheme = url_parse::scheme(&response.url);
+        urls.extend(response.hostnames.iter().map(|host| format!("{}://{}", scheme, host)));
+
+        urls.into_iter()
+            .filter(|url| self.add(url, global_opts))
+            .collect()
+    }
+
+    // Records the host of the url if it is in scope, or looks like it belongs
+    // to one of the targets' domains when no scope was given
+    // Returns true if the host hadn't been seen before
+    pub fn add(&mut self, url: &str, global_opts: &GlobalOpts) -> bool {
+        let host = url_parse::host(url).to_lowercase();
+        if host.is_empty() || self.scanned.contains(&host) || self.found.contains_key(&host) {
+            return false
+        }
+
+        let in_scope = match &global_opts.scope {
+            Some(scope) => scope.contains(&format!("{}/", url)),
+            None => self.domains.iter()
+                .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+        };
+        if in_scope {
+            self.found.insert(host, String::from(url));
+        }
+        in_scope
+    }
+
+    // Writes the url of each discovered host, one per line so that the
+    // file can be given to --host-file for a follow up scan
+    pub fn write(&self, filename: &str) {
+        let contents: String = self.found.values().map(|url| format!("{}\n", url)).collect();
+        if let Err(e) = fs::write(filename, contents) {
+            println!("Couldn't write discovered hosts to {}: {}", filename, e);
+        }
+    }
+}
+
+// Returns the domains the target hosts are registered under
+pub fn target_domains(global_opts: &GlobalOpts) -> Vec<String> {
+    let mut domains: Vec<String> = global_opts.hostnames.iter()
+        .filter_map(|hostname| url_parse::registered_domain(&url_parse::host(hostname)))
+        .collect();
+    domains.sort();
+    domains.dedup();
+    domains
+}
+
+// Generates a pattern matching names under any of the domains, or None if
+// there are no domains to search for
+pub fn hostname_pattern(domains: &[String]) -> Option<Regex> {
+    if domains.is_empty() { return None }
+
+    let domains: Vec<String> = domains.iter().map(|domain| regex::escape(domain)).collect();
+    Some(Regex::new(&format!(r"(?i)(?:^|[^a-z0-9.-])((?:[a-z0-9](?:[a-z0-9-]*[a-z0-9])?\.)*(?:{}))\b",
+        domains.join("|"))).unwrap())
+}
+
+// Returns each distinct hostname matching the pattern in the content
+pub fn hostnames_in(content: &str, pattern: &Regex) -> Vec<String> {
+    let hostnames: BTreeSet<String> = pattern.captures_iter(content)
+        .map(|captures| captures[1].to_lowercase())
+        .collect();
+    hostnames.into_iter().collect()
+}
+
+// Requests the url and returns the names in the subject and alternative
+// names of its certificate, wildcard names are left out
+pub fn certificate_hostnames(url: &str, global_opts: Arc<GlobalOpts>) -> Vec<String> {
+    let mut easy = request::generate_easy(global_opts);
+    easy.certinfo(true).unwrap();
+    request::make_request(&mut easy, String::from(url));
+
+    let mut hostnames = BTreeSet::new();
+    if let Some(certificate) = request::certificate_info(&easy) {
+        hostnames.extend(certificate.common_name());
+        hostnames.extend(certificate.names);
+    }
+
+    hostnames.into_iter().filter(|name| !name.starts_with('*') && name.contains('.')).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_hostnames_in() {
+        let pattern = super::hostname_pattern(&["example.com".into()]).unwrap();
+        let content = "<a href=\"https://staging.example.com/login\">API.example.com \
+            example.community notexample.com mail.example.com. a.example.com b.example.com";
+        assert_eq!(super::hostnames_in(content, &pattern),
+            vec!["a.example.com", "api.example.com", "b.example.com", "mail.example.com", "staging.example.com"]);
+    }
+}
@@ -0,0 +1,182 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+    net::{IpAddr, ToSocketAddrs},
+    process::exit,
+    sync::Arc
+};
+use rand::{thread_rng, Rng, distributions::Alphanumeric};
+use crate::arg_parse::GlobalOpts;
+use crate::request;
+use crate::url_parse;
+
+// Result of probing the root of a single host before the scan
+struct HostProbe {
+    url: String,
+    protocol: String,
+    code: u32,
+    server: String,
+    redirect: String,
+    latency_ms: u128,
+    error: Option<String>
+}
+
+// Requests / once on each target host and prints a table of the results,
+// optionally asking for confirmation before the scan is started
+pub fn probe_hosts(global_opts: Arc<GlobalOpts>) {
+    let mut easy = request::generate_easy(global_opts.clone());
+
+    let mut base_urls: Vec<String> = global_opts.hostnames.iter()
+        .map(|hostname| url_parse::base_url(hostname) + "/")
+        .collect();
+    base_urls.sort();
+    base_urls.dedup();
+
+    let probes: Vec<HostProbe> = base_urls.into_iter()
+        .map(|url| probe_host(&mut easy, url, &global_opts))
+        .collect();
+
+    print_probe_table(&probes);
+
+    if global_opts.confirm {
+        let unreachable = probes.iter().filter(|probe| probe.error.is_some()).count();
+        print!("Scan {} hosts ({} unreachable)? [y/N] ", probes.len(), unreachable);
+        io::stdout().flush().unwrap();
+
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer).unwrap();
+        let answer = answer.trim().to_lowercase();
+        if answer != "y" && answer != "yes" {
+            println!("Scan cancelled");
+            exit(0);
+        }
+    }
+}
+
+// The details of each host which responds are kept for the report, so that
+// its root isn't requested again once the scan starts
+fn probe_host(easy: &mut curl::easy::Easy2<request::Collector>, url: String, global_opts: &GlobalOpts) -> HostProbe {
+    let mut probe = HostProbe {
+        protocol: url_parse::scheme(&url),
+        url,
+        code: 0,
+        server: String::new(),
+        redirect: String::new(),
+        latency_ms: 0,
+        error: None
+    };
+
+    easy.url(&probe.url).unwrap();
+    easy.get_mut().headers.clear();
+    easy.certinfo(probe.url.starts_with("https://")).unwrap();
+    let result = easy.perform();
+    easy.certinfo(false).unwrap();
+    if let Err(e) = result {
+        probe.error = Some(e.description().to_string());
+        return probe;
+    }
+
+    let base_url = url_parse::base_url(&probe.url);
+    if global_opts.scanned_hosts.claim(&base_url) {
+        global_opts.scanned_hosts.record(&base_url, request::last_host_info(easy, &base_url));
+    }
+
+    probe.code = easy.response_code().unwrap();
+    probe.latency_ms = easy.total_time().unwrap().as_millis();
+    if let Ok(Some(redirect)) = easy.redirect_url() {
+        probe.redirect = redirect.to_string();
+    }
+
+    let headers = &easy.get_ref().headers;
+    let version = request::http_version(headers);
+    if !version.is_empty() {
+        probe.protocol = format!("{} {}", probe.protocol, version);
+    }
+    if let Some(server) = request::header_values(headers, "server").pop() {
+        probe.server = server;
+    }
+
+    probe
+}
+
+fn print_probe_table(probes: &[HostProbe]) {
+    let url_width = probes.iter().map(|probe| probe.url.len()).max().unwrap_or(0).max(4);
+    let server_width = probes.iter().map(|probe| probe.server.len()).max().unwrap_or(0).max(6);
+
+    println!("{:url_width$}  {:16}  {:>4}  {:>8}  {:server_width$}  Redirect",
+        "Host", "Protocol", "Code", "Latency", "Server",
+        url_width=url_width, server_width=server_width);
+
+    for probe in probes {
+        match &probe.error {
+            Some(error) => println!("{:url_width$}  Unreachable: {}",
+                probe.url, error, url_width=url_width),
+            None => println!("{:url_width$}  {:16}  {:>4}  {:>6}ms  {:server_width$}  {}",
+                probe.url, probe.protocol, probe.code, probe.latency_ms,
+                probe.server, probe.redirect,
+                url_width=url_width, server_width=server_width)
+        }
+    }
+    println!();
+}
+
+// Resolves a random subdomain of each target's domain and warns if it
+// resolves, as the target may then be a catch-all serving a default site
+pub fn dns_wildcard_check(global_opts: &GlobalOpts) {
+    let mut checked_domains = BTreeSet::new();
+
+    for hostname in &global_opts.hostnames {
+        let host = url_parse::host(hostname);
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+
+        // Addresses and single label names can't have wildcard records
+        let domain = match url_parse::registered_domain(host) {
+            Some(domain) => domain,
+            None => continue
+        };
+        if !checked_domains.insert(domain.clone()) { continue }
+
+        let random_label: String = thread_rng().sample_iter(&Alphanumeric)
+            .take(16)
+            .map(char::from)
+            .collect::<String>()
+            .to_lowercase();
+        let random_host = format!("{}.{}", random_label, domain);
+
+        let wildcard_addresses = resolve(&random_host);
+        if wildcard_addresses.is_empty() { continue }
+
+        if resolve(host).intersection(&wildcard_addresses).next().is_some() {
+            println!("Warning: {} resolves to the same address as {}, *.{} has a wildcard DNS record \
+                and {} may be a catch-all for any name", random_host, host, domain, host);
+        }
+        else {
+            println!("Warning: {} resolves, *.{} has a wildcard DNS record", random_host, domain);
+        }
+    }
+}
+
+// Returns the addresses the given name resolves to, or an empty set if it doesn't resolve
+fn resolve(host: &str) -> BTreeSet<IpAddr> {
+    match (host, 0).to_socket_addrs() {
+        Ok(addresses) => addresses.map(|address| address.ip()).collect(),
+        Err(_) => BTreeSet::new()
+    }
+}
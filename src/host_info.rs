@@ -0,0 +1,189 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    sync::Mutex
+};
+
+// The details of a host's certificate which are shown in the report
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    // The DNS names in the subject alternative names
+    pub names: Vec<String>,
+    pub expires: String
+}
+
+impl CertificateInfo {
+    // Reads the details from the "Name:value" fields curl gives for a
+    // certificate
+    pub fn from_fields(fields: &[String]) -> CertificateInfo {
+        let mut info = CertificateInfo::default();
+        for field in fields {
+            let (name, value) = match field.split_once(':') {
+                Some((name, value)) => (name, value.trim()),
+                None => continue
+            };
+            match name {
+                "Subject" => info.subject = String::from(value),
+                "Issuer" => info.issuer = String::from(value),
+                "Expire date" => info.expires = String::from(value),
+                "X509v3 Subject Alternative Name" => info.names = value.split(',')
+                    .filter_map(|name| name.trim().strip_prefix("DNS:"))
+                    .map(|name| name.to_lowercase())
+                    .collect(),
+                _ => {}
+            }
+        }
+        info
+    }
+
+    // Returns the common name from the subject, if it has one
+    pub fn common_name(&self) -> Option<String> {
+        self.subject.split(',')
+            .filter_map(|part| part.trim().strip_prefix("CN"))
+            .map(|name| name.trim_start_matches([' ', '=']).to_lowercase())
+            .next()
+    }
+}
+
+// What was learnt about a host from the first request made to its root
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostInfo {
+    // The HTTP version of the response, e.g. "HTTP/2"
+    pub http_version: String,
+    // The Server header, empty if it wasn't sent
+    pub server: String,
+    // Where the root redirects to when it's another host or scheme, as the
+    // site is usually served from there
+    pub redirect: Option<String>,
+    pub certificate: Option<CertificateInfo>,
+    // Each of the audited security headers with its value, None if the
+    // root didn't send it
+    pub security_headers: Vec<(String, Option<String>)>
+}
+
+// Headers a site is expected to send to protect its pages in browsers,
+// Strict-Transport-Security is only audited for https hosts
+pub const AUDITED_HEADERS: [&str; 6] = [
+    "Strict-Transport-Security",
+    "Content-Security-Policy",
+    "X-Frame-Options",
+    "X-Content-Type-Options",
+    "Referrer-Policy",
+    "Permissions-Policy"
+];
+
+// HSTS policies shorter than 180 days are reported as weak
+const MIN_HSTS_MAX_AGE: u64 = 15552000;
+
+// Returns what is wrong with the value of an audited header, if anything
+pub fn header_issue(name: &str, value: Option<&str>) -> Option<&'static str> {
+    let value = match value {
+        Some(value) => value.to_lowercase(),
+        None => return Some("missing")
+    };
+
+    match name {
+        "Strict-Transport-Security" => {
+            let max_age = value.split(';')
+                .find_map(|directive| directive.trim().strip_prefix("max-age="))
+                .and_then(|max_age| max_age.trim_matches('"').parse::<u64>().ok());
+            match max_age {
+                Some(max_age) if max_age >= MIN_HSTS_MAX_AGE => None,
+                _ => Some("max-age under 180 days")
+            }
+        },
+        "Content-Security-Policy" if value.contains("'unsafe-inline'") || value.contains("'unsafe-eval'") =>
+            Some("allows unsafe scripts"),
+        "X-Frame-Options" if value != "deny" && value != "sameorigin" => Some("not DENY or SAMEORIGIN"),
+        "X-Content-Type-Options" if value != "nosniff" => Some("not nosniff"),
+        "Referrer-Policy" if value.split(',').any(|policy| policy.trim() == "unsafe-url") => Some("sends full urls"),
+        _ => None
+    }
+}
+
+// The info of each host scanned, collected from the first request made to
+// the host so the report can show it before the results
+#[derive(Default)]
+pub struct ScannedHosts {
+    // A host maps to None while its first request is being made, or if
+    // no response was received
+    hosts: Mutex<BTreeMap<String, Option<HostInfo>>>
+}
+
+impl ScannedHosts {
+    pub fn new() -> ScannedHosts {
+        ScannedHosts::default()
+    }
+
+    // Returns true if the host hasn't been seen before, in which case the
+    // caller is expected to record its info
+    pub fn claim(&self, host: &str) -> bool {
+        let mut hosts = self.hosts.lock().unwrap();
+        if hosts.contains_key(host) { return false }
+        hosts.insert(String::from(host), None);
+        true
+    }
+
+    pub fn record(&self, host: &str, info: HostInfo) {
+        self.hosts.lock().unwrap().insert(String::from(host), Some(info));
+    }
+
+    // Returns each host with its info, ordered by host
+    pub fn collected(&self) -> Vec<(String, HostInfo)> {
+        self.hosts.lock().unwrap().iter()
+            .filter_map(|(host, info)| Some((host.clone(), info.clone()?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_from_fields() {
+        let fields: Vec<String> = vec![
+            "Subject:C = GB, CN = www.example.com".into(),
+            "Issuer:C = US, O = Let's Encrypt, CN = R3".into(),
+            "Start date:Jan  1 00:00:00 2024 GMT".into(),
+            "Expire date:Apr  1 00:00:00 2024 GMT".into(),
+            "X509v3 Subject Alternative Name:DNS:www.example.com, DNS:Mail.Example.com, IP Address:10.0.0.1".into()
+        ];
+        let info = super::CertificateInfo::from_fields(&fields);
+        assert_eq!(info.subject, "C = GB, CN = www.example.com");
+        assert_eq!(info.issuer, "C = US, O = Let's Encrypt, CN = R3");
+        assert_eq!(info.expires, "Apr  1 00:00:00 2024 GMT");
+        assert_eq!(info.names, vec!["www.example.com", "mail.example.com"]);
+        assert_eq!(info.common_name(), Some(String::from("www.example.com")));
+    }
+
+    #[test]
+    fn check_header_issue() {
+        assert_eq!(super::header_issue("Content-Security-Policy", None), Some("missing"));
+        assert_eq!(super::header_issue("Strict-Transport-Security", Some("max-age=31536000; includeSubDomains")), None);
+        assert_eq!(super::header_issue("Strict-Transport-Security", Some("max-age=3600")), Some("max-age under 180 days"));
+        assert_eq!(super::header_issue("Content-Security-Policy", Some("script-src 'self' 'unsafe-inline'")),
+            Some("allows unsafe scripts"));
+        assert_eq!(super::header_issue("X-Frame-Options", Some("SAMEORIGIN")), None);
+        assert_eq!(super::header_issue("X-Frame-Options", Some("ALLOW-FROM https://example.com")),
+            Some("not DENY or SAMEORIGIN"));
+        assert_eq!(super::header_issue("X-Content-Type-Options", Some("nosniff")), None);
+        assert_eq!(super::header_issue("Permissions-Policy", Some("camera=()")), None);
+    }
+}
@@ -16,19 +16,41 @@
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::{
-    collections::VecDeque,
-    sync::{Arc, mpsc::{self, Sender, Receiver}},
+    collections::{BTreeSet, HashMap, VecDeque},
+    sync::{Arc, mpsc::{self, Sender, Receiver, TryRecvError}},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 extern crate curl;
 mod arg_parse;
 mod request;
 mod wordlist;
 mod output;
+mod bearer_token;
+mod calibrate;
 mod content_parse;
+mod dates;
+mod discovered_hosts;
+mod hash;
+mod host_check;
+mod host_info;
+mod notify;
+mod output_bodies;
+mod output_elasticsearch;
 mod output_format;
+mod output_manifest;
+mod output_sqlite;
+mod output_template;
+mod prescan;
+mod rate_limit;
 mod request_thread;
+mod rules;
+mod scope;
+mod technologies;
+mod update;
+mod tui;
+mod url_parse;
+mod vhost;
 
 fn main() {
     // Read the arguments in using the arg_parse module
@@ -36,37 +58,93 @@ fn main() {
 
     output::startup_text(global_opts.clone());
 
+    if global_opts.dns_wildcard_check {
+        host_check::dns_wildcard_check(&global_opts);
+    }
+
+    if global_opts.probe_hosts {
+        host_check::probe_hosts(global_opts.clone());
+    }
+
+    if !global_opts.exclude_like_urls.is_empty() {
+        global_opts.exclude_like.fetch(&global_opts.exclude_like_urls, global_opts.clone());
+    }
+
     // Get the wordlist file from the arguments and open it
-    let mut wordlist:Vec<String> = Vec::new();
-    for wordlist_file in global_opts.wordlist_files.clone() {
-        wordlist.append(&mut wordlist::lines_from_file(wordlist_file));
+    // Streamed wordlists are read by each generator as it goes, so aren't
+    // sorted or deduplicated
+    let wordlist = if let Some(brute_force) = &global_opts.brute_force {
+        wordlist::Wordlist::Brute(Arc::new(brute_force.clone()))
+    }
+    else if global_opts.stream_wordlist {
+        wordlist::Wordlist::streamed(global_opts.wordlist_files.clone())
     }
-    wordlist.sort();
-    wordlist.dedup();
-    
-    let wordlist = Arc::new(wordlist);
+    else if global_opts.mmap_wordlist {
+        wordlist::Wordlist::Mapped(Arc::new(wordlist::MappedWordlist::open(&global_opts.wordlist_files)))
+    }
+    else {
+        let mut wordlist:Vec<String> = Vec::new();
+        for wordlist_file in global_opts.wordlist_files.clone() {
+            wordlist.append(&mut wordlist::lines_from_file(wordlist_file));
+        }
+        wordlist.append(&mut dates::date_words(&global_opts.date_patterns, global_opts.date_range));
+        wordlist.sort();
+        wordlist.dedup();
+        wordlist::Wordlist::Loaded(Arc::new(wordlist))
+    };
+
+    // Hosts are fingerprinted in the background when technologies are given,
+    // and only queued once the technologies they match are known
+    let mut fingerprinter = if global_opts.technologies.is_empty() { None }
+        else { Some(technologies::Fingerprinter::start(global_opts.clone())) };
+    let technology_wordlists = technologies::extra_wordlists(&global_opts.technologies, &wordlist);
 
     // Create a queue for URIs that need to be scanned
     let mut scan_queue: VecDeque<wordlist::UriGenerator> = VecDeque::new();
 
+    // Directories which still have generators queued or running, keyed by
+    // the url of the directory without a trailing /
+    let mut pending_directories: HashMap<String, PendingDirectory> = HashMap::new();
+
+    // The dashboard replaces the line by line output when enabled
+    let mut dashboard = if global_opts.tui { Some(tui::Dashboard::new(global_opts.clone())) } else { None };
+
     // Push the host URI to the scan queue
     for hostname in &global_opts.hostnames {
-        let mut depth = hostname.matches("/").count() as u32;
-        if hostname.ends_with("/") {
-            depth -= 1;
+        let generators = queue_host(hostname, &global_opts, &mut fingerprinter, &wordlist,
+            &mut scan_queue, &mut pending_directories);
+        if let Some(dashboard) = &mut dashboard {
+            dashboard.generators_queued(hostname, generators);
         }
+    }
 
-        for prefix in &global_opts.prefixes {
-            for extension in &global_opts.extensions {
-                for start_index in 0..global_opts.wordlist_split {
-                    scan_queue.push_back(
-                        wordlist::UriGenerator::new(hostname.clone(), String::from(prefix.clone()),
-                            String::from(extension.clone()), wordlist.clone(), 
-                            start_index, global_opts.wordlist_split, depth));
+    // Responses identical to many others in their directory aren't printed as they arrive
+    let mut duplicates = output::DuplicateFilter::new(global_opts.max_duplicates, global_opts.similarity);
+
+    let mut notifier = global_opts.bell_on
+        .map(|threshold| notify::Notifier::new(threshold, global_opts.desktop_notify));
+
+    // Collect other hosts belonging to the targets, starting with the names in their certificates
+    let mut discovered_hosts = None;
+    if global_opts.discovered_hosts_file.is_some() || global_opts.auto_add_in_scope {
+        let mut discovered = discovered_hosts::DiscoveredHosts::new(&global_opts);
+        for hostname in global_opts.hostnames.iter().filter(|hostname| hostname.starts_with("https://")) {
+            for name in discovered_hosts::certificate_hostnames(hostname, global_opts.clone()) {
+                let url = format!("https://{}", name);
+                if discovered.add(&url, &global_opts) && global_opts.auto_add_in_scope {
+                    queue_discovered_host(&url, &global_opts, &mut dashboard, &mut fingerprinter,
+                        &wordlist, &mut scan_queue, &mut pending_directories);
                 }
             }
         }
+        discovered_hosts = Some(discovered);
     }
+    // Probe bare targets for HTTP services in the background, each service
+    // found is scanned straight away
+    let mut prescan = if global_opts.prescan_targets.is_empty() { None }
+        else { Some(prescan::start(&global_opts.prescan_targets, &global_opts.prescan_ports,
+            global_opts.prescan_threads, global_opts.clone())) };
+
     // Create a channel for threads to communicate with the parent on
     // This is used to send information about ending threads and information on responses
     let (tx, rx): (Sender<request::RequestResponse>, Receiver<request::RequestResponse>) = mpsc::channel();
@@ -76,6 +154,10 @@ fn main() {
 
     let mut response_list: Vec<request::RequestResponse> = Vec::new();
 
+    // The raw form of each normalised url received, used to drop responses
+    // for other encodings of a resource which has already been found
+    let mut seen_urls: HashMap<String, String> = HashMap::new();
+
     let file_handles = output::create_files(global_opts.clone());
 
     // Loop of checking for messages from the threads,
@@ -86,36 +168,133 @@ fn main() {
         // Check for messages from the threads
         let reply = rx.try_recv();
         match reply {
-            Ok(message) => {
+            Ok(mut message) => {
                 // If a thread has sent end, then we can reduce the threads in use count
+                // Once every generator for a directory has ended, report it as complete
                 if message.url == "END" {
-                    threads_in_use -= 1; }
+                    threads_in_use -= 1;
+                    if let Some(dashboard) = &mut dashboard {
+                        dashboard.generator_finished(&message.redirect_url);
+                    }
+
+                    let directory = message.redirect_url;
+                    let finished = match pending_directories.get_mut(&directory) {
+                        Some(pending) => { pending.generators -= 1; pending.generators == 0 },
+                        None => false
+                    };
+                    if finished {
+                        let pending = pending_directories.remove(&directory).unwrap();
+                        let stats = output::DirectoryStats {
+                            url: directory + "/",
+                            found: pending.found,
+                            elapsed: pending.started.elapsed()
+                        };
+                        if global_opts.verbose {
+                            println!("Completed {}: {} items found in {:.1}s",
+                                stats.url, stats.found, stats.elapsed.as_secs_f64());
+                        }
+                        file_handles.send_directory_complete(&stats);
+
+                        // Subdirectories held back until the extensions used in this directory were known
+                        let extensions = pending.child_extensions();
+                        for (url, parent_depth) in pending.deferred_subdirectories {
+                            if global_opts.verbose && extensions.len() < pending.extensions.len() {
+                                println!("Scanning {} with extensions narrowed to: {}", url,
+                                    extensions.iter().filter(|extension| !extension.is_empty())
+                                        .cloned().collect::<Vec<String>>().join(" "));
+                            }
+                            let generators = queue_directory(&url, parent_depth, extensions.clone(), &global_opts,
+                                &wordlist, &mut scan_queue, &mut pending_directories);
+                            if let Some(dashboard) = &mut dashboard {
+                                dashboard.generators_queued(&url, generators);
+                            }
+                        }
+                    }
+                }
 
                 // If a thread sent anything else, then call the print_response function to deal with output
                 // If the response was a directory, create generators with each extension and add it to the scan queue
                 else { 
-                    if !global_opts.silent {
-                        match output::print_response(&message, global_opts.clone(),
-                            false, false, global_opts.is_terminal && !global_opts.no_color) {
-                            Some(output) => { println!("{}", output) },
-                            None => {}
+                    message.raw_url = message.url.clone();
+                    message.url = url_parse::normalise(&message.url);
+                    let first_raw_url = seen_urls.entry(message.url.clone()).or_insert_with(|| message.raw_url.clone());
+                    if *first_raw_url != message.raw_url {
+                        if global_opts.verbose {
+                            println!("{} is another encoding of {}, skipping", message.raw_url, first_raw_url);
+                        }
+                        continue;
+                    }
+
+                    if let Some(discovered) = &mut discovered_hosts {
+                        for url in discovered.add_response(&message, &global_opts) {
+                            if global_opts.auto_add_in_scope {
+                                queue_discovered_host(&url, &global_opts, &mut dashboard, &mut fingerprinter,
+                                    &wordlist, &mut scan_queue, &mut pending_directories);
+                            }
+                        }
+                    }
+
+                    if let Some(notifier) = &mut notifier {
+                        notifier.add_response(&message);
+                    }
+
+                    if let Some(dashboard) = &mut dashboard {
+                        dashboard.add_response(&message);
+                    }
+                    else if !global_opts.silent {
+                        match duplicates.add(&message) {
+                            0 => if let Some(output) = output::print_response(&message, global_opts.clone(),
+                                false, false, global_opts.is_terminal && !global_opts.no_color) {
+                                println!("{}", output)
+                            },
+                            1 => println!("Hiding further responses {} to {} (CODE:{}|SIZE:{})",
+                                if global_opts.similarity.is_some() { "similar" } else { "identical" },
+                                message.url, message.code, message.content_len),
+                            _ => {}
+                        }
+                    }
+                    if let Some(pending) = pending_directories.get_mut(&parent_directory(&message.url)) {
+                        pending.found += 1;
+                        if global_opts.narrow_extensions {
+                            pending.record_extension(&message);
                         }
                     }
-                    if message.is_directory && (!message.is_listable || global_opts.scan_listable) && !global_opts.disable_recursion {
-                        for prefix in &global_opts.prefixes {
-                            for extension in &global_opts.extensions {
-                                for start_index in 0..global_opts.wordlist_split {
-                                    scan_queue.push_back(
-                                        wordlist::UriGenerator::new(message.url.clone(), String::from(prefix.clone()),
-                                            String::from(extension.clone()), wordlist.clone(), 
-                                            start_index, global_opts.wordlist_split, message.parent_depth));
+
+                    // Directories are only recursed into if they were found by an allowed source
+                    let source = if message.found_from_listable { "scraped" } else { "wordlist" };
+                    let recurse_source = global_opts.recurse_from.iter().any(|allowed| allowed == source);
+
+                    if message.is_directory && (!message.is_listable || global_opts.scan_listable) && !global_opts.disable_recursion
+                        && recurse_source && !message.skip_recursion {
+                        // Subdirectories are scanned with the extensions of their parent
+                        // When narrowing, they wait until the parent has finished so that
+                        // the extensions its files were found with are known
+                        match pending_directories.get_mut(&parent_directory(&message.url)) {
+                            Some(parent) if global_opts.narrow_extensions => {
+                                parent.deferred_subdirectories.push((message.url.clone(), message.parent_depth));
+                            },
+                            parent => {
+                                let extensions = match parent {
+                                    Some(parent) => parent.extensions.clone(),
+                                    None => global_opts.extensions.clone()
+                                };
+                                let generators = queue_directory(&message.url, message.parent_depth, extensions,
+                                    &global_opts, &wordlist, &mut scan_queue, &mut pending_directories);
+                                if let Some(dashboard) = &mut dashboard {
+                                    dashboard.generators_queued(&message.url, generators);
                                 }
                             }
                         }
                     }
                     else if message.is_listable && global_opts.verbose && !global_opts.scan_listable 
-                    { println!("{} is listable, skipping scanning", message.redirect_url); }
-                    
+                    { println!("{} is listable, skipping scanning", message.url); }
+                    else if message.is_directory && global_opts.verbose && !recurse_source
+                    { println!("{} was {}, skipping scanning", message.url,
+                        if source == "scraped" { "scraped" } else { "found from the wordlist" }); }
+                    else if message.skip_recursion && global_opts.verbose
+                    { println!("{} matched a no-recurse rule, skipping scanning", message.url); }
+
+                    file_handles.send_response(&message);
                     response_list.push(message);
                 }
             },
@@ -123,9 +302,39 @@ fn main() {
             Err(_) => {},
         };
 
+        // Queue services found by the pre-scan, it's finished once every worker has stopped
+        if let Some(services) = &prescan {
+            match services.try_recv() {
+                Ok(url) => {
+                    let in_scope = global_opts.scope.as_ref().is_none_or(|scope| scope.contains(&url));
+                    if in_scope {
+                        if dashboard.is_none() && !global_opts.silent {
+                            println!("Found HTTP service {}", url);
+                        }
+                        let generators = queue_host(&url, &global_opts, &mut fingerprinter, &wordlist,
+                            &mut scan_queue, &mut pending_directories);
+                        if let Some(dashboard) = &mut dashboard {
+                            dashboard.generators_queued(&url, generators);
+                        }
+                    }
+                },
+                Err(TryRecvError::Disconnected) => prescan = None,
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+
+        // Queue hosts which have been fingerprinted
+        if let Some((url, matched)) = fingerprinter.as_mut().and_then(|fingerprinter| fingerprinter.try_recv()) {
+            let generators = queue_fingerprinted_host(&url, &matched, &global_opts, &wordlist,
+                &technology_wordlists, &mut scan_queue, &mut pending_directories);
+            if let Some(dashboard) = &mut dashboard {
+                dashboard.generators_queued(&url, generators);
+            }
+        }
+
         // If there are items in the scan queue and available threads
         // Spawn a new thread to scan an item
-        if threads_in_use < global_opts.max_threads && scan_queue.len() > 0 {
+        if threads_in_use < global_opts.max_threads && !scan_queue.is_empty() {
 
             // Clone a new sender to the channel and a new wordlist reference
             // Then pop the scan target from the queue
@@ -134,18 +343,215 @@ fn main() {
             let arg_clone = global_opts.clone();
 
             // Spawn a thread with the arguments and increment the in use counter
-            thread::spawn(|| request_thread::thread_spawn(tx_clone, list_gen, arg_clone));
+            if global_opts.vhost {
+                thread::spawn(|| vhost::thread_spawn(tx_clone, list_gen, arg_clone));
+            }
+            else {
+                thread::spawn(|| request_thread::thread_spawn(tx_clone, list_gen, arg_clone));
+            }
             threads_in_use += 1;
         }
 
         // If there are no threads in use and the queue is empty then stop
-        if threads_in_use == 0 && scan_queue.len() == 0 {
+        if threads_in_use == 0 && scan_queue.is_empty() && prescan.is_none()
+            && fingerprinter.as_ref().is_none_or(|fingerprinter| fingerprinter.is_finished()) {
             break;
         }
 
+        // Redraw the dashboard, stopping the scan early if the user quits
+        if let Some(dashboard) = &mut dashboard {
+            if !dashboard.update(threads_in_use) {
+                break;
+            }
+        }
+
         // Sleep to reduce CPU cycles used by main
         thread::sleep(Duration::from_millis(1));
     }
 
+    if let Some(dashboard) = dashboard {
+        dashboard.close();
+    }
+
+    // Check a sample of the findings give the same code to a HEAD request
+    if let Some(sample_size) = global_opts.head_check {
+        let (checked, inconsistent) = request::head_check(&mut response_list, global_opts.clone(), sample_size);
+        if !global_opts.silent {
+            for (url, get_code, head_code) in &inconsistent {
+                println!("HEAD and GET differ for {} (GET:{}|HEAD:{})", url, get_code, head_code);
+            }
+            println!("{} of {} sampled findings responded differently to HEAD requests", inconsistent.len(), checked);
+            if !inconsistent.is_empty() {
+                println!("Results from HEAD requests to this target may not be trustworthy");
+            }
+        }
+    }
+
+    if let (Some(discovered), Some(filename)) = (&discovered_hosts, &global_opts.discovered_hosts_file) {
+        discovered.write(filename);
+    }
+
     output::print_report(response_list, global_opts.clone(), file_handles);
-}
\ No newline at end of file
+
+    if let Some(filename) = &global_opts.manifest_file {
+        output_manifest::write_manifest(filename, &global_opts);
+    }
+}
+
+// Returns the depth of the path a host url starts at
+fn host_depth(hostname: &str) -> u32 {
+    let mut depth = hostname.matches("/").count() as u32;
+    if hostname.ends_with("/") {
+        depth -= 1;
+    }
+    depth
+}
+
+// Queues a host found during the scan to be scanned like the targets
+fn queue_discovered_host(url: &str, global_opts: &arg_parse::GlobalOpts, dashboard: &mut Option<tui::Dashboard>,
+    fingerprinter: &mut Option<technologies::Fingerprinter>, wordlist: &wordlist::Wordlist,
+    scan_queue: &mut VecDeque<wordlist::UriGenerator>, pending_directories: &mut HashMap<String, PendingDirectory>) {
+    if dashboard.is_none() && !global_opts.silent {
+        println!("Adding discovered host {} to the scan", url);
+    }
+    let generators = queue_host(url, global_opts, fingerprinter, wordlist, scan_queue, pending_directories);
+    if let Some(dashboard) = dashboard {
+        dashboard.generators_queued(url, generators);
+    }
+}
+
+// Queues the root of a host, or hands it to the fingerprinter when
+// technologies are given so that it's queued once they're known
+// Returns the number of generators added
+fn queue_host(url: &str, global_opts: &arg_parse::GlobalOpts, fingerprinter: &mut Option<technologies::Fingerprinter>,
+    wordlist: &wordlist::Wordlist, scan_queue: &mut VecDeque<wordlist::UriGenerator>,
+    pending_directories: &mut HashMap<String, PendingDirectory>) -> usize
+{
+    if let Some(fingerprinter) = fingerprinter {
+        fingerprinter.add(url);
+        return 0
+    }
+    queue_directory(url, host_depth(url), global_opts.extensions.clone(), global_opts,
+        wordlist, scan_queue, pending_directories)
+}
+
+// Queues the root of a fingerprinted host, adding the extensions and the
+// words of each technology it matched to the usual ones
+// The extensions carry on to its subdirectories, the wordlists are only
+// used on the root. Returns the number of generators added
+fn queue_fingerprinted_host(url: &str, matched: &[usize], global_opts: &arg_parse::GlobalOpts,
+    wordlist: &wordlist::Wordlist, technology_wordlists: &[Option<wordlist::Wordlist>],
+    scan_queue: &mut VecDeque<wordlist::UriGenerator>, pending_directories: &mut HashMap<String, PendingDirectory>) -> usize
+{
+    let mut extensions = global_opts.extensions.clone();
+    for index in matched {
+        let technology = &global_opts.technologies[*index];
+        if !global_opts.silent {
+            tui::message(format!("Detected {} on {}", technology.name, url));
+        }
+        extensions.extend(technology.extensions.iter().cloned());
+    }
+    extensions.sort();
+    extensions.dedup();
+
+    let mut generators = queue_directory(url, host_depth(url), extensions.clone(), global_opts,
+        wordlist, scan_queue, pending_directories);
+    for technology_wordlist in matched.iter().filter_map(|index| technology_wordlists[*index].as_ref()) {
+        generators += queue_directory(url, host_depth(url), extensions.clone(), global_opts,
+            technology_wordlist, scan_queue, pending_directories);
+    }
+    generators
+}
+
+// Adds generators for each prefix and extension of the given directory to the scan queue
+// and records the directory as pending. Returns the number of generators added
+fn queue_directory(url: &str, parent_depth: u32, extensions: Vec<String>, global_opts: &arg_parse::GlobalOpts,
+    wordlist: &wordlist::Wordlist, scan_queue: &mut VecDeque<wordlist::UriGenerator>,
+    pending_directories: &mut HashMap<String, PendingDirectory>) -> usize
+{
+    for prefix in &global_opts.prefixes {
+        for extension in &extensions {
+            for start_index in 0..global_opts.wordlist_split {
+                scan_queue.push_back(
+                    wordlist::UriGenerator::new(String::from(url), prefix.clone(),
+                        extension.clone(), wordlist.clone(),
+                        start_index, global_opts.wordlist_split, parent_depth));
+            }
+        }
+    }
+
+    let generators = global_opts.prefixes.len() * extensions.len() * global_opts.wordlist_split as usize;
+    pending_directories.entry(url.trim_end_matches('/').to_string())
+        .or_insert_with(|| PendingDirectory::new(extensions)).generators += generators;
+    generators
+}
+
+// Number of files which must be found in a directory before the extensions
+// used for its subdirectories are narrowed
+const NARROWING_MIN_FILES: usize = 3;
+
+// Progress of a directory which is being scanned
+struct PendingDirectory {
+    generators: usize,
+    found: usize,
+    started: Instant,
+    // Extensions the directory is being scanned with
+    extensions: Vec<String>,
+    // Number of files found in the directory, and the extensions they were found with
+    files: usize,
+    file_extensions: BTreeSet<String>,
+    // Subdirectories and their depths which are queued once the directory finishes
+    deferred_subdirectories: Vec<(String, u32)>
+}
+
+impl PendingDirectory {
+    fn new(extensions: Vec<String>) -> PendingDirectory {
+        PendingDirectory {
+            generators: 0,
+            found: 0,
+            started: Instant::now(),
+            extensions,
+            files: 0,
+            file_extensions: BTreeSet::new(),
+            deferred_subdirectories: Vec::new()
+        }
+    }
+
+    // Records which extension a file found in the directory was requested with
+    fn record_extension(&mut self, response: &request::RequestResponse) {
+        if response.is_directory || response.found_from_listable
+            || response.code < 200 || response.code >= 400 {
+            return
+        }
+
+        self.files += 1;
+        if let Some(extension) = self.extensions.iter()
+            .filter(|extension| !extension.is_empty() && response.url.ends_with(extension.as_str()))
+            .max_by_key(|extension| extension.len())
+        {
+            self.file_extensions.insert(extension.clone());
+        }
+    }
+
+    // Returns the extensions to scan subdirectories with. Once enough files have been
+    // found, only the extensions they used are kept along with the bare words
+    fn child_extensions(&self) -> Vec<String> {
+        if self.files < NARROWING_MIN_FILES || self.file_extensions.is_empty() {
+            return self.extensions.clone()
+        }
+
+        self.extensions.iter()
+            .filter(|extension| extension.is_empty() || self.file_extensions.contains(*extension))
+            .cloned()
+            .collect()
+    }
+}
+
+// Returns the url of the directory containing the given url, without a trailing /
+fn parent_directory(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    match url.rfind('/') {
+        Some(index) => String::from(&url[..index]),
+        None => String::from(url)
+    }
+}
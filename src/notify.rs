@@ -0,0 +1,89 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant}
+};
+use crate::output_format::{self, Severity};
+use crate::request::RequestResponse;
+
+// Findings arriving in a burst only ring the bell or show a notification once
+const ALERT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Alerts the user to findings at or above a severity, for scans left
+// running in a background terminal
+pub struct Notifier {
+    threshold: Severity,
+    desktop: bool,
+    last_alert: Option<Instant>,
+    // Findings which came too soon after the last alert to get their own
+    suppressed: usize
+}
+
+impl Notifier {
+    pub fn new(threshold: Severity, desktop: bool) -> Notifier {
+        Notifier { threshold, desktop, last_alert: None, suppressed: 0 }
+    }
+
+    pub fn add_response(&mut self, response: &RequestResponse) {
+        let severity = output_format::severity(response);
+        if severity < self.threshold { return }
+
+        if self.last_alert.is_some_and(|last_alert| last_alert.elapsed() < ALERT_INTERVAL) {
+            self.suppressed += 1;
+            return
+        }
+
+        if self.desktop {
+            let mut message = format!("{} finding: {}", severity.as_str(), response.url);
+            if self.suppressed > 0 {
+                message += &format!(" and {} more", self.suppressed);
+            }
+            desktop_notification(&message);
+        }
+        else {
+            print!("\x07");
+            io::stdout().flush().ok();
+        }
+        self.last_alert = Some(Instant::now());
+        self.suppressed = 0;
+    }
+}
+
+// Shows a desktop notification using the platform's notification tool,
+// failures are ignored as the finding is still reported as usual
+fn desktop_notification(message: &str) {
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!("display notification \"{}\" with title \"Dirble\"",
+            message.replace('\\', "\\\\").replace('"', "\\\"")));
+        command
+    }
+    else {
+        let mut command = Command::new("notify-send");
+        command.args(["Dirble", message]);
+        command
+    };
+
+    // Wait for the tool in the background so it doesn't hold up the scan
+    if let Ok(mut child) = command.stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+        thread::spawn(move || child.wait());
+    }
+}
@@ -15,21 +15,98 @@
 // You should have received a copy of the GNU General Public License
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, mpsc::{self, Sender}};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 use crate::request::RequestResponse;
 use crate::arg_parse::GlobalOpts;
+use crate::content_parse;
+use crate::host_info::{self, CertificateInfo, HostInfo};
+use crate::output_bodies;
 use crate::output_format;
+use crate::output_elasticsearch;
+use crate::output_sqlite;
+use crate::output_template;
+use crate::url_parse;
 use std::error::Error;
-use std::io::{LineWriter, Write};
+use std::io::{BufWriter, Write};
 
-// Struct giving access to each current file handle
-// Will be extended in future with handles for different formats
+// Messages which can be passed to an output writer thread
+#[derive(Clone)]
+pub enum WriterMessage {
+    // A single response, sent to streaming writers as soon as it is received
+    Response(Box<RequestResponse>),
+    // Sent to streaming writers whenever every generator for a directory has finished
+    DirectoryComplete(DirectoryStats),
+    // The complete sorted list of responses, sent at the end of the scan
+    Report(Arc<Vec<RequestResponse>>)
+}
+
+// Statistics for a directory whose scan has completed
+#[derive(Clone)]
+pub struct DirectoryStats {
+    pub url: String,
+    // Number of items discovered directly within the directory
+    pub found: usize,
+    // Time between the directory being queued and its scan finishing
+    pub elapsed: Duration
+}
+
+// Function which writes a report in a single format to the given file
+type ReportFunction = fn(&mut BufWriter<File>, &[RequestResponse], Arc<GlobalOpts>);
+
+// Function which writes a single response to the given file as it arrives
+type ResponseFunction = fn(&mut BufWriter<File>, &RequestResponse, Arc<GlobalOpts>);
+
+// Function which writes a directory completion event to the given file
+type DirectoryFunction = fn(&mut BufWriter<File>, &DirectoryStats);
+
+// A writer thread which owns a single output file
+// Each format gets its own thread and queue so that a slow sink
+// can't hold up the other formats or the terminal output
+pub struct OutputWriter {
+    sender: Sender<WriterMessage>,
+    thread: JoinHandle<()>,
+    streaming: bool
+}
+
+// Struct giving access to each current output writer
 pub struct FileHandles {
-    pub output_file: Option<LineWriter<File>>,
-    pub json_file: Option<LineWriter<File>>,
-    pub xml_file: Option<LineWriter<File>>
+    pub writers: Vec<OutputWriter>
+}
+
+impl FileHandles {
+    // Sends a message to every writer thread
+    pub fn send(&self, message: WriterMessage) {
+        for writer in &self.writers {
+            writer.sender.send(message.clone()).unwrap();
+        }
+    }
+
+    // Sends a copy of a response to each writer which outputs responses as they arrive
+    pub fn send_response(&self, response: &RequestResponse) {
+        for writer in self.writers.iter().filter(|writer| writer.streaming) {
+            writer.sender.send(WriterMessage::Response(Box::new(response.clone()))).unwrap();
+        }
+    }
+
+    // Tells each streaming writer that a directory has finished so it can flush its output
+    pub fn send_directory_complete(&self, stats: &DirectoryStats) {
+        for writer in self.writers.iter().filter(|writer| writer.streaming) {
+            writer.sender.send(WriterMessage::DirectoryComplete(stats.clone())).unwrap();
+        }
+    }
+
+    // Closes the queue for each writer and waits for them to finish writing
+    pub fn close(self) {
+        for writer in self.writers {
+            drop(writer.sender);
+            writer.thread.join().unwrap();
+        }
+    }
 }
 
 pub fn print_response(response: &RequestResponse, global_opts: Arc<GlobalOpts>, 
@@ -51,65 +128,512 @@ pub fn print_response(response: &RequestResponse, global_opts: Arc<GlobalOpts>,
     Some(output)
 }
 
+type DuplicateKey = (String, u32, usize, String);
+
+// Counts responses with the same code, size and body in a directory, so that
+// the pages of templated sites don't flood the terminal
+// Directories are never counted as they're needed to follow the scan
+pub struct DuplicateFilter {
+    limit: u32,
+    // The --similarity threshold, responses with bodies this similar are
+    // counted with the first response like them
+    similarity: Option<u32>,
+    // The count for each distinct response along with its body's simhash
+    // Kept sorted so that responses like several others are always counted
+    // with the same one
+    counts: BTreeMap<DuplicateKey, (u32, u64)>
+}
+
+impl DuplicateFilter {
+    // A limit of 0 shows every response
+    pub fn new(limit: u32, similarity: Option<u32>) -> DuplicateFilter {
+        DuplicateFilter { limit, similarity, counts: BTreeMap::new() }
+    }
+
+    // Counts the response, returning how many identical responses are over
+    // the limit including it, or 0 if it should be shown
+    pub fn add(&mut self, response: &RequestResponse) -> u32 {
+        if self.limit == 0 || response.is_directory || response.body_sha256.is_empty() { return 0 }
+        let (count, _) = self.counts.entry(self.duplicate_key(response)).or_insert((0, response.body_simhash));
+        *count += 1;
+        count.saturating_sub(self.limit)
+    }
+
+    // Returns how many responses identical to the response are over the limit
+    pub fn hidden(&self, response: &RequestResponse) -> u32 {
+        self.counts.get(&self.duplicate_key(response)).map_or(0, |(count, _)| count.saturating_sub(self.limit))
+    }
+
+    // Responses with a similar body to one already counted in the directory
+    // with the same code share its key
+    fn duplicate_key(&self, response: &RequestResponse) -> DuplicateKey {
+        let key = (directory_name(response), response.code, response.content_len, response.body_sha256.clone());
+        let similarity = match self.similarity {
+            Some(similarity) => similarity,
+            None => return key
+        };
+
+        self.counts.iter()
+            .find(|((directory, code, _, _), (_, simhash))| *directory == key.0 && *code == key.1
+                && content_parse::similar(*simhash, response.body_simhash, similarity))
+            .map_or(key, |(similar_key, _)| similar_key.clone())
+    }
+}
+
 // Called after a scan to print the discovered items in a sorted way - deals with saving to files too
 pub fn print_report(responses: Vec<RequestResponse>, global_opts: Arc<GlobalOpts>, file_handles: FileHandles) {
-    let responses = sort_responses(responses);
+    let responses = Arc::new(sort_responses(responses));
+
+    // Hand the report to each of the writer threads before printing
+    // so that files are written while the terminal report is output
+    file_handles.send(WriterMessage::Report(responses.clone()));
 
     if (!global_opts.silent || global_opts.verbose) && global_opts.is_terminal {
         println!("\n");
     }
 
-    let report_string = String::from("Dirble Scan Report: \n");
-
     // If stdout is a terminal then write a report to it
     if global_opts.is_terminal
     {
-        println!("{}", report_string);
-        for response in &responses {
+        println!("{}", report_header(&global_opts));
+
+        // Identical responses over the limit are replaced with a line
+        // giving how many were left out, where the first of them would be
+        let mut totals = DuplicateFilter::new(global_opts.max_duplicates, global_opts.similarity);
+        for response in responses.iter() {
+            totals.add(response);
+        }
+        let mut duplicates = DuplicateFilter::new(global_opts.max_duplicates, global_opts.similarity);
+        let likeness = if global_opts.similarity.is_some() { "similar" } else { "identical" };
+        for response in responses.iter() {
+            match duplicates.add(response) {
+                0 => {},
+                1 => {
+                    println!("  x{} more {} responses (CODE:{}|SIZE:{}) in {}/", totals.hidden(response), likeness,
+                        response.code, response.content_len, directory_name(response));
+                    continue
+                },
+                _ => continue
+            }
             if let Some(line) = print_response(&response, global_opts.clone(), 
                 true, false, !global_opts.no_color) {
                 println!("{}", line);
             }
         }
     }
-    
-    
-    // If it was provided, write to a normally formatted output file
-    if let Some(mut handle) = file_handles.output_file {
-        write_file(&mut handle, report_string);
 
-        for response in &responses {
-            if let Some(line) = print_response(&response, global_opts.clone()
-                , true, true, false) {
-                let file_line = format!("{}\n", line);
-                write_file(&mut handle, file_line);
+    if global_opts.header_summary && global_opts.is_terminal {
+        for line in header_summary(&responses) {
+            println!("{}", line);
+        }
+    }
+
+    if global_opts.is_terminal {
+        for line in redirect_hosts(&responses, &global_opts) {
+            println!("{}", line);
+        }
+        for line in error_frameworks(&responses) {
+            println!("{}", line);
+        }
+    }
+
+    // Wait for all of the files to be completely written
+    file_handles.close();
+}
+
+// Summarises the cookies and security headers set in each directory,
+// only listing a directory if they differ from its parent directory
+pub fn header_summary(responses: &[RequestResponse]) -> Vec<String> {
+    let mut directories: BTreeMap<String, (BTreeSet<String>, BTreeSet<String>)> = BTreeMap::new();
+    for response in responses {
+        let entry = directories.entry(directory_name(response)).or_default();
+        entry.0.extend(response.cookie_names.iter().cloned());
+        entry.1.extend(response.security_headers.iter().cloned());
+    }
+
+    let mut lines = vec![String::from("\nCookies and security headers by directory:")];
+    for (directory, sets) in &directories {
+        // Find the closest parent directory which had responses
+        let mut parent = None;
+        let mut path = directory.as_str();
+        while let Some(index) = path.rfind('/') {
+            path = &path[..index];
+            if path.ends_with('/') { break }
+            if let Some(parent_sets) = directories.get(path) {
+                parent = Some(parent_sets);
+                break
             }
         }
+
+        let differs = match parent {
+            Some(parent_sets) => parent_sets != sets,
+            None => !sets.0.is_empty() || !sets.1.is_empty()
+        };
+        if differs {
+            lines.push(format!("  {}/ - Cookies: {} | Headers: {}",
+                directory, join_or_none(&sets.0), join_or_none(&sets.1)));
+        }
+    }
+
+    if lines.len() == 1 {
+        lines.push(String::from("  No cookies or security headers were set"));
+    }
+    lines
+}
+
+// Lists each host which responses redirected to, with the number of redirects
+// to it, noting hosts which weren't among the scanned hosts
+pub fn redirect_hosts(responses: &[RequestResponse], global_opts: &GlobalOpts) -> Vec<String> {
+    let mut hosts: BTreeMap<String, usize> = BTreeMap::new();
+    for response in responses.iter().filter(|response| !response.redirect_url.is_empty()) {
+        *hosts.entry(url_parse::authority(&response.redirect_url)).or_insert(0) += 1;
+    }
+    if hosts.is_empty() { return Vec::new() }
+
+    let scanned_hosts: BTreeSet<String> = global_opts.hostnames.iter()
+        .map(|hostname| url_parse::authority(hostname))
+        .collect();
+
+    let mut lines = vec![String::from("\nRedirect destination hosts:")];
+    for (host, count) in &hosts {
+        let external = if scanned_hosts.contains(host) { "" } else { " - not scanned" };
+        lines.push(format!("  {} ({} redirect{}){}", host, count, if *count == 1 { "" } else { "s" }, external));
+    }
+    lines
+}
+
+// Lists the frameworks identified from server error pages on each host
+pub fn error_frameworks(responses: &[RequestResponse]) -> Vec<String> {
+    let mut hosts: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    for response in responses {
+        if let Some(framework) = &response.framework {
+            *hosts.entry(url_parse::base_url(&response.url)).or_default()
+                .entry(framework.clone()).or_insert(0) += 1;
+        }
+    }
+    if hosts.is_empty() { return Vec::new() }
+
+    let mut lines = vec![String::from("\nFrameworks identified from error pages:")];
+    for (host, frameworks) in &hosts {
+        let frameworks: Vec<String> = frameworks.iter()
+            .map(|(framework, count)| format!("{} ({} page{})", framework, count, if *count == 1 { "" } else { "s" }))
+            .collect();
+        lines.push(format!("  {} - {}", host, frameworks.join(", ")));
+    }
+    lines
+}
+
+#[inline]
+fn join_or_none(values: &BTreeSet<String>) -> String {
+    if values.is_empty() {
+        String::from("none")
+    }
+    else {
+        values.iter().cloned().collect::<Vec<String>>().join(", ")
+    }
+}
+
+const REPORT_HEADER: &str = "Dirble Scan Report: \n";
+
+// The report header, followed by the tags the scan was run with and the
+// info of each host
+fn report_header(global_opts: &GlobalOpts) -> String {
+    let mut header = String::from(REPORT_HEADER);
+    if !global_opts.tags.is_empty() {
+        header += &format!("Tags: {}\n", output_format::format_tags(&global_opts.tags, " "));
+    }
+    for (host, info) in global_opts.scanned_hosts.collected() {
+        header += &format!("Host {}:\n{}", host, host_details(&info, "  "));
+    }
+    header
+}
+
+// Lists the info of a host, one detail per line starting with the prefix,
+// with the certificate's details nested beneath it
+fn host_details(info: &HostInfo, prefix: &str) -> String {
+    let mut details = format!("{prefix}Protocol: {}\n{prefix}Server: {}\n",
+        if info.http_version.is_empty() { "unknown" } else { &info.http_version },
+        if info.server.is_empty() { "none" } else { &info.server },
+        prefix=prefix);
+    if let Some(redirect) = &info.redirect {
+        details += &format!("{}Redirects to: {}\n", prefix, redirect);
+    }
+    if let Some(certificate) = &info.certificate {
+        details += &format!("{}Certificate:\n{}", prefix, certificate_details(certificate, &format!("  {}", prefix)));
+    }
+    if !info.security_headers.is_empty() {
+        details += &format!("{}Security headers:\n{}", prefix,
+            security_header_details(&info.security_headers, &format!("  {}", prefix)));
+    }
+    details
+}
+
+// Lists each audited header with its value and any issue with it, one per
+// line with the given indent
+fn security_header_details(headers: &[(String, Option<String>)], indent: &str) -> String {
+    headers.iter()
+        .map(|(name, value)| match (value, host_info::header_issue(name, value.as_deref())) {
+            (Some(value), Some(issue)) => format!("{}{}: {} ({})\n", indent, name, value, issue),
+            (Some(value), None) => format!("{}{}: {}\n", indent, name, value),
+            (None, _) => format!("{}{}: missing\n", indent, name)
+        })
+        .collect()
+}
+
+// Lists the details of a certificate, one per line with the given indent
+fn certificate_details(certificate: &CertificateInfo, indent: &str) -> String {
+    let names = if certificate.names.is_empty() { String::from("none") } else { certificate.names.join(", ") };
+    format!("{indent}Subject: {}\n{indent}Issuer: {}\n{indent}Names: {}\n{indent}Expires: {}\n",
+        certificate.subject, certificate.issuer, names, certificate.expires, indent=indent)
+}
+
+// Writes a normally formatted report to the given file
+fn write_text_report(handle: &mut BufWriter<File>, responses: &[RequestResponse], global_opts: Arc<GlobalOpts>) {
+    write_file(handle, report_header(&global_opts));
+
+    for response in responses {
+        if let Some(line) = print_response(response, global_opts.clone()
+            , true, true, false) {
+            let file_line = format!("{}\n", line);
+            write_file(handle, file_line);
+        }
+    }
+
+    if global_opts.header_summary {
+        for line in header_summary(responses) {
+            write_file(handle, format!("{}\n", line));
+        }
+    }
+
+    for line in redirect_hosts(responses, &global_opts) {
+        write_file(handle, format!("{}\n", line));
+    }
+
+    for line in error_frameworks(responses) {
+        write_file(handle, format!("{}\n", line));
+    }
+}
+
+// Writes the responses to the given file as a JSON array
+fn write_json_report(handle: &mut BufWriter<File>, responses: &[RequestResponse], global_opts: Arc<GlobalOpts>) {
+    let lines: Vec<String> = responses.iter()
+        .map(|response| output_format::output_json(response, &global_opts.tags))
+        .collect();
+    write_file(handle, format!("[{}]", lines.join(",\n")));
+}
+
+// Writes the responses to the given file as an XML document
+// Directories contain the elements for the items found inside them
+fn write_xml_report(handle: &mut BufWriter<File>, responses: &[RequestResponse], global_opts: Arc<GlobalOpts>) {
+    write_file(handle, String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    write_file(handle, String::from("<dirble_scan>\n"));
+    if !global_opts.tags.is_empty() {
+        let tags: String = global_opts.tags.iter()
+            .map(|(name, value)| format!("    <tag name=\"{}\">{}</tag>\n",
+                output_format::xml_escape(name), output_format::xml_escape(value)))
+            .collect();
+        write_file(handle, format!("<tags>\n{}</tags>\n", tags));
+    }
+    let hosts = global_opts.scanned_hosts.collected();
+    if !hosts.is_empty() {
+        let elements: String = hosts.iter()
+            .map(|(host, info)| output_format::output_xml_host(host, info))
+            .collect();
+        write_file(handle, format!("<hosts>\n{}</hosts>\n", elements));
+    }
+    for element in xml_tree(responses) {
+        write_file(handle, element);
+    }
+    write_file(handle, String::from("</dirble_scan>"));
+}
+
+// Builds the XML elements for the top level responses, with each
+// response nested inside the element of its closest parent directory
+fn xml_tree(responses: &[RequestResponse]) -> Vec<String> {
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); responses.len()];
+    let mut top_level = Vec::new();
+    for (index, parent_index) in parent_indices(responses).into_iter().enumerate() {
+        match parent_index {
+            Some(parent_index) => children[parent_index].push(index),
+            None => top_level.push(index)
+        }
     }
 
-    if let Some(mut handle) = file_handles.json_file {
-        write_file(&mut handle, String::from("["));
-        for response in &responses[0..responses.len()-1] {
-            let line = format!("{},\n", output_format::output_json(response));
-            write_file(&mut handle, line);
+    top_level.iter()
+        .map(|&index| xml_element(responses, &children, index))
+        .collect()
+}
+
+// Finds the index of the closest parent directory of each response
+// None is given for responses with no parent directory in the list
+fn parent_indices(responses: &[RequestResponse]) -> Vec<Option<usize>> {
+    // Map each directory to its index in the responses
+    let mut directories: BTreeMap<String, usize> = BTreeMap::new();
+    for (index, response) in responses.iter().enumerate() {
+        if response.is_directory {
+            directories.insert(directory_name(response), index);
         }
-        let final_line = format!("{}]", output_format::output_json(&responses[responses.len()-1]));
-        write_file(&mut handle, final_line);
     }
 
-    if let Some(mut handle) = file_handles.xml_file {
-        write_file(&mut handle, String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
-        write_file(&mut handle, String::from("<dirble_scan>\n"));
-        for response in &responses {
-            write_file(&mut handle, output_format::output_xml(response));
+    responses.iter()
+        .map(|response| {
+            let mut path = directory_name(response);
+            if !response.is_directory {
+                path.push('/');
+            }
+
+            while let Some(slash) = path.rfind('/') {
+                path.truncate(slash);
+                if path.ends_with('/') { break }
+                if let Some(&directory_index) = directories.get(&path) {
+                    return Some(directory_index)
+                }
+            }
+            None
+        })
+        .collect()
+}
+
+// Writes the responses as a graphviz DOT graph, with each response
+// linked to its parent directory or the host it was found on
+fn write_dot_report(handle: &mut BufWriter<File>, responses: &[RequestResponse], _global_opts: Arc<GlobalOpts>) {
+    write_file(handle, String::from("digraph dirble {\n    rankdir=LR;\n    node [style=filled, fontname=\"monospace\"];\n"));
+
+    let mut hosts = BTreeSet::new();
+    for (index, parent_index) in parent_indices(responses).into_iter().enumerate() {
+        let response = &responses[index];
+        write_file(handle, format!("    {}\n", output_format::output_dot(response, index)));
+
+        let parent = match parent_index {
+            Some(parent_index) => format!("n{}", parent_index),
+            None => {
+                let host = url_parse::base_url(&response.url);
+                let host_node = format!("\"{}\"", output_format::dot_escape(&host));
+                if hosts.insert(host) {
+                    write_file(handle, format!("    {} [shape=house, fillcolor=white];\n", host_node));
+                }
+                host_node
+            }
+        };
+        write_file(handle, format!("    {} -> n{};\n", parent, index));
+    }
+
+    write_file(handle, String::from("}\n"));
+}
+
+fn xml_element(responses: &[RequestResponse], children: &[Vec<usize>], index: usize) -> String {
+    let child_elements: Vec<String> = children[index].iter()
+        .map(|&child| xml_element(responses, children, child))
+        .collect();
+    output_format::output_xml(&responses[index], &child_elements)
+}
+
+// Writes the responses to the given file as CSV with a header row
+// Each tag is added as a column with the same value on every row
+fn write_csv_report(handle: &mut BufWriter<File>, responses: &[RequestResponse], global_opts: Arc<GlobalOpts>) {
+    let tag_names: String = global_opts.tags.iter()
+        .map(|(name, _)| format!(",{}", output_format::csv_escape(name)))
+        .collect();
+    let tag_values: String = global_opts.tags.iter()
+        .map(|(_, value)| format!(",{}", output_format::csv_escape(value)))
+        .collect();
+
+    write_file(handle, format!("{}{}\n", output_format::CSV_HEADER, tag_names));
+    for response in responses {
+        write_file(handle, format!("{}{}\n", output_format::output_csv(response), tag_values));
+    }
+}
+
+// Writes a markdown report with a summary table followed by
+// a section for each host, grouped into tables by directory
+fn write_markdown_report(handle: &mut BufWriter<File>, responses: &[RequestResponse], global_opts: Arc<GlobalOpts>) {
+    let mut hosts: BTreeMap<String, Vec<&RequestResponse>> = BTreeMap::new();
+    for response in responses {
+        hosts.entry(url_parse::base_url(&response.url)).or_default().push(response);
+    }
+
+    write_file(handle, String::from("# Dirble Scan Report\n\n"));
+    if !global_opts.tags.is_empty() {
+        write_file(handle, format!("Tags: {}\n\n",
+            output_format::markdown_escape(&output_format::format_tags(&global_opts.tags, ", "))));
+    }
+    write_file(handle, String::from("## Summary\n\n"));
+    write_file(handle, String::from("| Host | Directories | Listable | Files | Scraped |\n|---|---|---|---|---|\n"));
+    for (host, host_responses) in &hosts {
+        let directories = host_responses.iter().filter(|r| r.is_directory).count();
+        let listable = host_responses.iter().filter(|r| r.is_listable).count();
+        let scraped = host_responses.iter().filter(|r| !r.is_directory && r.found_from_listable).count();
+        let files = host_responses.len() - directories - scraped;
+        write_file(handle, format!("| {} | {} | {} | {} | {} |\n",
+            output_format::markdown_escape(host), directories, listable, files, scraped));
+    }
+
+    let host_info: BTreeMap<String, HostInfo> = global_opts.scanned_hosts.collected().into_iter().collect();
+    for (host, host_responses) in &hosts {
+        write_file(handle, format!("\n## {}\n", output_format::markdown_escape(host)));
+        if let Some(info) = host_info.get(host) {
+            write_file(handle, format!("\n{}", output_format::markdown_escape(&host_details(info, "- "))));
+        }
+
+        let mut current_directory = None;
+        for response in host_responses {
+            let directory = directory_name(response);
+            if current_directory.as_ref() != Some(&directory) {
+                write_file(handle, format!("\n### {}/\n\n{}\n",
+                    output_format::markdown_escape(url_parse::path(&directory).trim_end_matches('/')),
+                    output_format::MARKDOWN_TABLE_HEADER));
+                current_directory = Some(directory);
+            }
+            write_file(handle, format!("{}\n", output_format::output_markdown(response)));
         }
-        write_file(&mut handle, String::from("</dirble_scan>"));
     }
 }
 
-// Write a string to the provided LineWriter
+// Writes the responses in Burp Suite's site map format so they can be imported
+fn write_burp_report(handle: &mut BufWriter<File>, responses: &[RequestResponse], _global_opts: Arc<GlobalOpts>) {
+    write_file(handle, format!("{}\n", output_format::BURP_HEADER));
+    for response in responses {
+        write_file(handle, output_format::output_burp(response));
+    }
+    write_file(handle, String::from("</items>\n"));
+}
+
+// Writes the responses to the given file as a JUnit XML report
+fn write_junit_report(handle: &mut BufWriter<File>, responses: &[RequestResponse], global_opts: Arc<GlobalOpts>) {
+    write_file(handle, output_format::output_junit(responses, &global_opts.junit_rules));
+}
+
+// Writes the responses to the given file as a SARIF log
+fn write_sarif_report(handle: &mut BufWriter<File>, responses: &[RequestResponse], global_opts: Arc<GlobalOpts>) {
+    write_file(handle, output_format::output_sarif(responses, &global_opts.tags));
+}
+
+// Writes a response as a single line of JSON, flushing it so the file can be followed live
+fn write_jsonl_response(handle: &mut BufWriter<File>, response: &RequestResponse, global_opts: Arc<GlobalOpts>) {
+    write_file(handle, format!("{}\n", output_format::output_json(response, &global_opts.tags)));
+    handle.flush().unwrap();
+}
+
+fn write_jsonl_directory(handle: &mut BufWriter<File>, stats: &DirectoryStats) {
+    write_file(handle, format!("{}\n", output_format::output_directory_event(stats)));
+}
+
+// Writes a response as a single tab separated line, flushing it so the file can be followed live
+// Tags are added as a final field when given
+fn write_grep_response(handle: &mut BufWriter<File>, response: &RequestResponse, global_opts: Arc<GlobalOpts>) {
+    let tags = if global_opts.tags.is_empty() { String::new() }
+        else { format!("\tTags: {}", output_format::format_tags(&global_opts.tags, ",")) };
+    write_file(handle, format!("{}{}\n", output_format::output_grep(response), tags));
+    handle.flush().unwrap();
+}
+
+fn write_grep_directory(handle: &mut BufWriter<File>, stats: &DirectoryStats) {
+    write_file(handle, format!("# Completed: {}\tFound: {}\n", stats.url, stats.found));
+}
+
+// Write a string to the provided BufWriter
 #[inline]
-fn write_file(file_writer: &mut LineWriter<File>, line: String) {
+fn write_file(file_writer: &mut BufWriter<File>, line: String) {
     let write_line = line.as_bytes();
     file_writer.write_all(write_line).unwrap();
 }
@@ -141,33 +665,131 @@ pub fn directory_name(response:&RequestResponse) -> String
     }
 }
 
-// Returns a FileHandles struct with LineWriters for each specified output type
+// Returns a FileHandles struct with a writer thread for each specified output type
 pub fn create_files(global_opts: Arc<GlobalOpts>) -> FileHandles {
-    let mut output_file = None;
+    let mut writers = Vec::new();
 
     if let Some(filename) = &global_opts.output_file {
-        output_file = generate_handle(filename);
+        writers.push(spawn_writer(filename, global_opts.clone(), write_text_report));
     }
 
-    let mut json_file = None;
     if let Some(filename) = &global_opts.json_file {
-        json_file = generate_handle(filename);
+        writers.push(spawn_writer(filename, global_opts.clone(), write_json_report));
     }
 
-    let mut xml_file = None;
     if let Some(filename) = &global_opts.xml_file {
-        xml_file = generate_handle(filename);
+        writers.push(spawn_writer(filename, global_opts.clone(), write_xml_report));
+    }
+
+    if let Some(filename) = &global_opts.csv_file {
+        writers.push(spawn_writer(filename, global_opts.clone(), write_csv_report));
+    }
+
+    if let Some(filename) = &global_opts.markdown_file {
+        writers.push(spawn_writer(filename, global_opts.clone(), write_markdown_report));
+    }
+
+    if let Some(filename) = &global_opts.burp_file {
+        writers.push(spawn_writer(filename, global_opts.clone(), write_burp_report));
+    }
+
+    if let Some(filename) = &global_opts.dot_file {
+        writers.push(spawn_writer(filename, global_opts.clone(), write_dot_report));
+    }
+
+    if let Some(filename) = &global_opts.junit_file {
+        writers.push(spawn_writer(filename, global_opts.clone(), write_junit_report));
+    }
+
+    if let Some(filename) = &global_opts.sarif_file {
+        writers.push(spawn_writer(filename, global_opts.clone(), write_sarif_report));
+    }
+
+    if let Some(filename) = &global_opts.jsonl_file {
+        writers.push(spawn_stream_writer(filename, global_opts.clone(),
+            write_jsonl_response, write_jsonl_directory));
+    }
+
+    if let Some(filename) = &global_opts.grep_file {
+        writers.push(spawn_stream_writer(filename, global_opts.clone(),
+            write_grep_response, write_grep_directory));
+    }
+
+    if let Some(filename) = &global_opts.sqlite_file {
+        writers.push(output_sqlite::spawn_writer(filename, &global_opts.tags));
+    }
+
+    if let Some(url) = &global_opts.elasticsearch_url {
+        writers.push(output_elasticsearch::spawn_writer(&global_opts, url));
+    }
+
+    if let Some((template_file, filename)) = &global_opts.output_template {
+        writers.push(output_template::spawn_writer(template_file, filename, &global_opts.tags));
+    }
+
+    if let Some(directory) = &global_opts.save_bodies {
+        output_bodies::create_directory(directory);
     }
 
     FileHandles {
-        output_file: output_file,
-        json_file: json_file,
-        xml_file: xml_file
+        writers
+    }
+}
+
+// Starts a writer thread which writes a report when the scan is finished
+fn spawn_writer(filename: &String, global_opts: Arc<GlobalOpts>, 
+    write_report: ReportFunction) -> OutputWriter
+{
+    let mut handle = generate_handle(filename);
+    start_writer(false, move |message| {
+        if let WriterMessage::Report(responses) = message {
+            write_report(&mut handle, &responses, global_opts.clone());
+            handle.flush().unwrap();
+        }
+    })
+}
+
+// Starts a writer thread which writes each response as soon as it is received
+fn spawn_stream_writer(filename: &String, global_opts: Arc<GlobalOpts>, 
+    write_response: ResponseFunction, write_directory: DirectoryFunction) -> OutputWriter
+{
+    let mut handle = generate_handle(filename);
+    start_writer(true, move |message| {
+        match message {
+            WriterMessage::Response(response) => {
+                write_response(&mut handle, &response, global_opts.clone());
+            },
+            WriterMessage::DirectoryComplete(stats) => {
+                write_directory(&mut handle, &stats);
+                handle.flush().unwrap();
+            },
+            WriterMessage::Report(_) => {}
+        }
+    })
+}
+
+// Starts a thread which calls the write function whenever a message is
+// received on its queue, streaming writers are also sent each response
+pub fn start_writer<F>(streaming: bool, mut write: F) -> OutputWriter
+    where F: FnMut(WriterMessage) + Send + 'static
+{
+    let (tx, rx) = mpsc::channel::<WriterMessage>();
+
+    let thread = thread::spawn(move || {
+        for message in rx {
+            write(message);
+        }
+    });
+
+    OutputWriter {
+        sender: tx,
+        thread,
+        streaming
     }
 }
 
 #[inline]
-fn generate_handle(filename: &String) -> Option<LineWriter<File>>
+pub fn generate_handle(filename: &String) -> BufWriter<File>
 {
     let path = Path::new(&filename);
     let display = path.display();
@@ -175,7 +797,7 @@ fn generate_handle(filename: &String) -> Option<LineWriter<File>>
         Err(why) => panic!("couldn't create {}: {}",
                            display,
                            why.description()),
-        Ok(file) => Some(LineWriter::new(file)),
+        Ok(file) => BufWriter::new(file),
     }
 }
 
@@ -187,7 +809,21 @@ pub fn startup_text(global_opts: Arc<GlobalOpts>) {
     println!("Developed by Izzy Whistlecroft\n");
 
     println!("Hosts: {}", global_opts.hostnames.clone().join(" "));
-    println!("Wordlists: {}", global_opts.wordlist_files.clone().join(" "));
+    if !global_opts.prescan_targets.is_empty() {
+        println!("Probing {} bare targets on ports: {}", global_opts.prescan_targets.len(),
+            global_opts.prescan_ports.iter().map(|port| port.to_string()).collect::<Vec<String>>().join(" "));
+    }
+    match &global_opts.brute_force {
+        Some(brute_force) => println!("Brute forcing: {} up to {} characters",
+            brute_force.charset.iter().collect::<String>(), brute_force.max_length),
+        None => println!("Wordlists: {}", global_opts.wordlist_files.clone().join(" "))
+    }
+    if global_opts.vhost {
+        match &global_opts.vhost_domain {
+            Some(domain) if !domain.is_empty() => println!("Scanning for virtual hosts under: {}", domain),
+            _ => println!("Scanning for virtual hosts")
+        }
+    }
 
     if global_opts.prefixes.len() == 1 && global_opts.prefixes[0] == "" {
         println!("No Prefixes");
@@ -203,4 +839,61 @@ pub fn startup_text(global_opts: Arc<GlobalOpts>) {
         println!("Extensions: {}", global_opts.extensions.clone()[1..].join(" "));
     }
     println!("");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::request::RequestResponse;
+
+    fn response(url: &str, is_directory: bool) -> RequestResponse {
+        RequestResponse {
+            url: url.into(),
+            code: 200,
+            is_directory,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn check_xml_nesting() {
+        let responses = super::sort_responses(vec![
+            response("http://example.com/index.html", false),
+            response("http://example.com/admin/", true),
+            response("http://example.com/admin/login.php", false),
+            response("http://example.com/admin/users/", true),
+            response("http://example.com/admin/users/list.php", false)
+        ]);
+        let tree = super::xml_tree(&responses);
+
+        assert_eq!(tree.len(), 2, "Only the root file and directory should be top level");
+        let admin = &tree[1];
+        assert!(admin.starts_with("<directory url=\"http://example.com/admin/\" "));
+        assert!(admin.contains("\n    <file url=\"http://example.com/admin/login.php\" "));
+        assert!(admin.contains("\n    <directory url=\"http://example.com/admin/users/\" "));
+        assert!(admin.contains("\n        <file url=\"http://example.com/admin/users/list.php\" "));
+    }
+
+    #[test]
+    fn check_duplicate_filter() {
+        let mut duplicates = super::DuplicateFilter::new(2, None);
+        let page = |url: &str| RequestResponse { body_sha256: "ab".into(), ..response(url, false) };
+
+        assert_eq!(duplicates.add(&page("http://example.com/a")), 0);
+        assert_eq!(duplicates.add(&page("http://example.com/b")), 0);
+        assert_eq!(duplicates.add(&page("http://example.com/c")), 1);
+        assert_eq!(duplicates.add(&page("http://example.com/d")), 2);
+        // Other directories and directories themselves are counted separately
+        assert_eq!(duplicates.add(&page("http://example.com/admin/a")), 0);
+        assert_eq!(duplicates.add(&response("http://example.com/e/", true)), 0);
+        assert_eq!(duplicates.hidden(&page("http://example.com/a")), 2);
+
+        // Bodies which differ slightly are counted together given a similarity
+        let mut similar = super::DuplicateFilter::new(1, Some(90));
+        let token = |url: &str, sha: &str, size, simhash| RequestResponse {
+            body_sha256: sha.into(), content_len: size, body_simhash: simhash, ..response(url, false)
+        };
+        assert_eq!(similar.add(&token("http://example.com/a", "ab", 100, 0xff00ff00)), 0);
+        assert_eq!(similar.add(&token("http://example.com/b", "cd", 101, 0xff00ff01)), 1);
+        assert_eq!(similar.add(&token("http://example.com/c", "ef", 100, !0xff00ff00)), 0);
+    }
+}
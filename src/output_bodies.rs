@@ -0,0 +1,104 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::Path,
+    process::exit
+};
+use crate::hash;
+use crate::request::RequestResponse;
+use crate::tui;
+
+// Name of the file in the directory listing which url each body is from
+const INDEX_FILE: &str = "index.txt";
+
+// Creates the directory bodies are saved to and empties its index, exiting
+// if either fails
+pub fn create_directory(directory: &str)
+{
+    if let Err(e) = fs::create_dir_all(directory) {
+        println!("Creating directory \"{}\" failed: {}", directory, e);
+        exit(2);
+    }
+    let index = Path::new(directory).join(INDEX_FILE);
+    if let Err(e) = File::create(&index) {
+        println!("Creating file \"{}\" failed: {}", index.display(), e);
+        exit(2);
+    }
+}
+
+// Writes the body of a reported response to a file named from the hash of
+// its url, and records the url the file is for in the index
+// Bodies shorter than the response because the buffer cap was reached are
+// marked as truncated in the index
+// Failures are reported without stopping the scan
+pub fn save(directory: &str, response: &RequestResponse, body: &[u8])
+{
+    let url = &response.url;
+    let name = body_filename(url);
+    let truncated = body.len() < response.content_len;
+    if let Err(e) = write_body(directory, &name, url, body, truncated) {
+        tui::message(format!("Couldn't save the body of {}: {}", url, e));
+    }
+}
+
+fn write_body(directory: &str, name: &str, url: &str, body: &[u8], truncated: bool) -> io::Result<()>
+{
+    let directory = Path::new(directory);
+    fs::write(directory.join(name), body)?;
+
+    // Each line is written at once so lines from different threads aren't mixed
+    let mut index = OpenOptions::new().create(true).append(true).open(directory.join(INDEX_FILE))?;
+    let marker = if truncated { "\ttruncated" } else { "" };
+    index.write_all(format!("{}\t{}{}\n", name, url, marker).as_bytes())
+}
+
+// Returns the path of the index and of each body listed in it
+pub fn saved_files(directory: &str) -> Vec<String>
+{
+    let directory = Path::new(directory);
+    let index = directory.join(INDEX_FILE);
+    let mut names: Vec<String> = fs::read_to_string(&index).unwrap_or_default().lines()
+        .filter_map(|line| line.split('\t').next())
+        .map(|name| directory.join(name).to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names.dedup();
+    if index.exists() {
+        names.push(index.to_string_lossy().into_owned());
+    }
+    names
+}
+
+// Generates the filename for a url, the url itself can't be used as it may
+// contain characters which aren't allowed in filenames
+fn body_filename(url: &str) -> String
+{
+    hash::sha256(url.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_body_filename() {
+        assert_eq!(super::body_filename("http://example.com/"),
+            "2a1b402420ef46577471cdc7409b0fa2c6a204db316e59ade2d805435489a067");
+        assert_ne!(super::body_filename("http://example.com/a"), super::body_filename("http://example.com/b"));
+    }
+}
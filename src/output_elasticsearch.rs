@@ -0,0 +1,125 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    io::Read,
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+use curl::easy::{Easy, List};
+use crate::arg_parse::GlobalOpts;
+use crate::output::{self, OutputWriter, WriterMessage};
+use crate::output_format;
+use crate::request::RequestResponse;
+use crate::url_parse;
+use crate::tui;
+
+// Number of responses sent in each bulk request
+const BATCH_SIZE: usize = 500;
+
+// Starts a writer thread which indexes responses into Elasticsearch using
+// the bulk API. Responses are sent in batches, with any remaining responses
+// sent when a directory completes or the scan finishes
+pub fn spawn_writer(global_opts: &GlobalOpts, base_url: &str) -> OutputWriter
+{
+    let bulk_url = format!("{}/_bulk", base_url.trim_end_matches('/'));
+    let action = format!("{{\"index\": {{\"_index\": \"{}\"}}}}\n",
+        output_format::json_escape(&global_opts.elasticsearch_index));
+    let timestamp = output_format::format_timestamp(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
+
+    let mut easy = Easy::new();
+    easy.url(&bulk_url).unwrap();
+    easy.post(true).unwrap();
+    easy.timeout(Duration::from_secs(30)).unwrap();
+    let mut headers = List::new();
+    headers.append("Content-Type: application/x-ndjson").unwrap();
+    easy.http_headers(headers).unwrap();
+    if let Some(auth) = &global_opts.elasticsearch_auth {
+        let mut credentials = auth.splitn(2, ':');
+        easy.username(credentials.next().unwrap()).unwrap();
+        easy.password(credentials.next().unwrap_or("")).unwrap();
+    }
+
+    let tags = global_opts.tags.clone();
+    let mut body = String::new();
+    let mut pending = 0;
+
+    output::start_writer(true, move |message| {
+        match message {
+            WriterMessage::Response(response) => {
+                body += &action;
+                body += &document(&response, &timestamp, &tags);
+                body += "\n";
+
+                pending += 1;
+                if pending >= BATCH_SIZE {
+                    send_bulk(&mut easy, &bulk_url, &body);
+                    body.clear();
+                    pending = 0;
+                }
+            },
+            WriterMessage::DirectoryComplete(_) | WriterMessage::Report(_) => {
+                if pending > 0 {
+                    send_bulk(&mut easy, &bulk_url, &body);
+                    body.clear();
+                    pending = 0;
+                }
+            }
+        }
+    })
+}
+
+// Generates the document indexed for a response, which is the JSON output
+// with the time of the scan and the host the response came from
+fn document(response: &RequestResponse, timestamp: &str, tags: &[(String, String)]) -> String
+{
+    format!("{{\"@timestamp\": \"{}\", \"host\": \"{}\", {}",
+        timestamp,
+        output_format::json_escape(&url_parse::base_url(&response.url)),
+        &output_format::output_json(response, tags)[1..])
+}
+
+// Sends a bulk request, reporting any failure to the user without stopping the scan
+fn send_bulk(easy: &mut Easy, bulk_url: &str, body: &str)
+{
+    let mut data = body.as_bytes();
+    let mut reply = Vec::new();
+    easy.post_field_size(data.len() as u64).unwrap();
+
+    let result = {
+        let mut transfer = easy.transfer();
+        transfer.read_function(|buf| Ok(data.read(buf).unwrap_or(0))).unwrap();
+        transfer.write_function(|received| {
+            reply.extend_from_slice(received);
+            Ok(received.len())
+        }).unwrap();
+        transfer.perform()
+    };
+
+    match result {
+        Err(e) => tui::message(format!("Indexing results into {} failed: {}", bulk_url, e)),
+        Ok(_) => {
+            let code = easy.response_code().unwrap();
+            let reply = String::from_utf8_lossy(&reply);
+            if code >= 300 {
+                tui::message(format!("Indexing results into {} failed with code {}: {}", bulk_url, code, reply.trim()));
+            }
+            else if reply.contains("\"errors\":true") {
+                tui::message(format!("Some results could not be indexed into {}", bulk_url));
+            }
+        }
+    }
+}
@@ -15,7 +15,12 @@
 // You should have received a copy of the GNU General Public License
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
+use crate::dates;
+use crate::host_info::{self, HostInfo};
+use crate::output::DirectoryStats;
 use crate::request::RequestResponse;
+use crate::url_parse;
 use colored::*;
 
 #[inline]
@@ -66,7 +71,8 @@ pub fn output_url(response: &RequestResponse) -> String {
 
 #[inline]
 pub fn output_suffix(response: &RequestResponse, color: bool) -> String {
-    if response.found_from_listable { return String::from("(SCRAPED)") }
+    // Scraped urls which weren't verified with a request have no code to show
+    if response.found_from_listable && response.code == 0 { return String::from("(SCRAPED)") }
 
     let mut code_string:String = format!{"{}", response.code};
     if color {
@@ -79,55 +85,854 @@ pub fn output_suffix(response: &RequestResponse, color: bool) -> String {
         }
     }
 
-    match response.code {
-        301 | 302 => {
-            format!("(CODE:{}|SIZE:{:#?}|DEST:{})", 
+    let mut suffix = match redirect_type(response) {
+        Some("internal") => {
+            format!("(CODE:{}|SIZE:{:#?}|DEST:{}", 
                 code_string, response.content_len, response.redirect_url)
         }
+        Some(redirect_type) => {
+            format!("(CODE:{}|SIZE:{:#?}|DEST:{}|{}", 
+                code_string, response.content_len, response.redirect_url, redirect_type.to_uppercase())
+        }
         _ => {
-            format!("(CODE:{}|SIZE:{:#?})", code_string, response.content_len)
+            format!("(CODE:{}|SIZE:{:#?}", code_string, response.content_len)
         }
+    };
+
+    if compressed(response) {
+        suffix += &format!("|TRANSFERRED:{}", response.transfer_size);
+    }
+
+    if response.time_flagged {
+        let time_string = format!("{}ms", response.response_time);
+        suffix += &format!("|TIME:{}", if color { time_string.yellow().to_string() } else { time_string });
+    }
+
+    if !response.content_type.is_empty() {
+        suffix += &format!("|TYPE:{}", media_type(&response.content_type));
+    }
+
+    if !response.server.is_empty() {
+        suffix += &format!("|SERVER:{}", response.server);
+    }
+
+    if !response.title.is_empty() {
+        suffix += &format!("|TITLE:{}", response.title);
+    }
+
+    if !response.verb_codes.is_empty() {
+        let verb_codes: Vec<String> = response.verb_codes.iter()
+            .map(|(verb, code)| format!("{}={}", verb, code))
+            .collect();
+        suffix += &format!("|VERBS:{}", verb_codes.join(","));
+    }
+
+    if !response.allowed_methods.is_empty() {
+        let methods: Vec<String> = response.allowed_methods.iter()
+            .map(|method| if color && WRITE_METHODS.contains(&method.as_str()) {
+                method.red().to_string()
+            }
+            else {
+                method.clone()
+            })
+            .collect();
+        suffix += &format!("|ALLOW:{}", methods.join(","));
+    }
+
+    if !response.cors.is_empty() {
+        let cors = response.cors.join(",");
+        suffix += &format!("|CORS:{}", if color { cors.red().to_string() } else { cors });
+    }
+
+    if !response.bypasses.is_empty() {
+        let bypasses: Vec<String> = response.bypasses.iter()
+            .map(|(url, code)| format!("{}={}", url, code))
+            .collect();
+        let bypass_string = bypasses.join(",");
+        suffix += &format!("|BYPASS:{}", if color { bypass_string.red().to_string() } else { bypass_string });
+    }
+
+    if !response.keywords.is_empty() {
+        suffix += &format!("|KEYWORDS:{}", response.keywords.join(","));
+    }
+
+    if let Some(framework) = &response.framework {
+        suffix += &format!("|FRAMEWORK:{}", framework);
+    }
+
+    if !response.endpoint_hints.is_empty() {
+        suffix += &format!("|HINTS:{}", response.endpoint_hints.join(","));
+    }
+
+    if response.is_listable {
+        suffix += &format!("|FILES:{}", response.listable_entries);
+        if let Some(size) = response.listable_size {
+            suffix += &format!("|TOTAL:~{}", human_size(size));
+        }
+    }
+
+    suffix + ")"
+}
+
+// Checks whether the body was sent compressed, so fewer bytes were
+// transferred than its size
+pub fn compressed(response: &RequestResponse) -> bool {
+    response.transfer_size != 0 && response.transfer_size != response.content_len
+}
+
+// Returns the media type of a Content-Type without its parameters, e.g.
+// "text/html" for "text/html; charset=utf-8"
+pub fn media_type(content_type: &str) -> &str {
+    content_type.split(';').next().unwrap_or("").trim()
+}
+
+// Checks a Content-Type against a media type given by the user, which can
+// be exact, e.g. application/json, or cover every subtype, e.g. image/* or
+// just image
+pub fn media_type_matches(pattern: &str, content_type: &str) -> bool {
+    let media_type = media_type(content_type).to_lowercase();
+    let pattern = pattern.trim().to_lowercase();
+    match pattern.strip_suffix("/*") {
+        Some(main_type) => media_type.split('/').next() == Some(main_type),
+        None if !pattern.contains('/') => media_type.split('/').next() == Some(pattern.as_str()),
+        None => media_type == pattern
     }
 }
 
+// Returns where the response redirects to relative to the url, or None if it
+// isn't a redirect
+pub fn redirect_type(response: &RequestResponse) -> Option<&'static str> {
+    if response.redirect_url.is_empty() { return None }
+    Some(url_parse::redirect_type(&response.url, &response.redirect_url))
+}
+
+// Formats a number of bytes in the largest whole unit
+fn human_size(size: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 { format!("{} {}", size, units[unit]) }
+    else { format!("{:.1} {}", size, units[unit]) }
+}
+
+// Generates the XML element for a response, directories contain
+// the elements of their children which are given already formatted
 #[inline]
-pub fn output_xml(response: &RequestResponse) -> String {
-    format!("<file url=\"{}\">
+pub fn output_xml(response: &RequestResponse, children: &[String]) -> String {
+    let element = if response.is_directory { "directory" } else { "file" };
+
+    let mut child_elements = String::new();
+    for child in children {
+        for line in child.lines() {
+            child_elements += &format!("    {}\n", line);
+        }
+    }
+
+    format!("<{} url=\"{}\" raw_url=\"{}\">
     <status_code>{}</status_code>
+    <http_version>{}</http_version>
+    <content_type>{}</content_type>
+    <server>{}</server>
+    <title>{}</title>
     <size>{}</size>
+    <transfer_size>{}</transfer_size>
+    <response_time>{}</response_time>
+    <body_sha256>{}</body_sha256>
     <is_directory>{}</is_directory>
     <is_listable>{}</is_listable>
     <found_from_listable>{}</found_from_listable>
     <redirect_url>{}</redirect_url>
-</file>\n", 
-    response.url,
+    <redirect_type>{}</redirect_type>
+    <listable_entries>{}</listable_entries>
+    <listable_size>{}</listable_size>
+    <verb_codes>{}</verb_codes>
+    <bypasses>{}</bypasses>
+    <allowed_methods>{}</allowed_methods>
+    <cors>{}</cors>
+    <framework>{}</framework>
+{}{}</{}>\n", 
+    element,
+    xml_escape(&response.url),
+    xml_escape(&response.raw_url),
     response.code,
+    xml_escape(&response.http_version),
+    xml_escape(&response.content_type),
+    xml_escape(&response.server),
+    xml_escape(&response.title),
     response.content_len,
+    response.transfer_size,
+    response.response_time,
+    response.body_sha256,
     response.is_directory,
     response.is_listable,
     response.found_from_listable,
-    response.redirect_url)
+    xml_escape(&response.redirect_url),
+    redirect_type(response).unwrap_or(""),
+    response.listable_entries,
+    response.listable_size.map_or(String::new(), |size| size.to_string()),
+    response.verb_codes.iter()
+        .map(|(verb, code)| format!("<verb name=\"{}\">{}</verb>", xml_escape(verb), code))
+        .collect::<String>(),
+    response.bypasses.iter()
+        .map(|(url, code)| format!("<bypass url=\"{}\">{}</bypass>", xml_escape(url), code))
+        .collect::<String>(),
+    response.allowed_methods.iter()
+        .map(|method| format!("<method>{}</method>", xml_escape(method)))
+        .collect::<String>(),
+    response.cors.iter()
+        .map(|origin| format!("<origin>{}</origin>", xml_escape(origin)))
+        .collect::<String>(),
+    xml_escape(response.framework.as_deref().unwrap_or("")),
+    xml_headers(&response.headers),
+    child_elements,
+    element)
+}
+
+// Generates the XML element for the info of a host
+pub fn output_xml_host(host: &str, info: &HostInfo) -> String {
+    let redirect = match &info.redirect {
+        Some(redirect) => format!("        <redirect>{}</redirect>\n", xml_escape(redirect)),
+        None => String::new()
+    };
+    let certificate = match &info.certificate {
+        Some(certificate) => {
+            let names: String = certificate.names.iter()
+                .map(|name| format!("                <name>{}</name>\n", xml_escape(name)))
+                .collect();
+            format!("        <certificate>
+            <subject>{}</subject>
+            <issuer>{}</issuer>
+            <expires>{}</expires>
+            <names>\n{}            </names>
+        </certificate>\n",
+                xml_escape(&certificate.subject),
+                xml_escape(&certificate.issuer),
+                xml_escape(&certificate.expires),
+                names)
+        },
+        None => String::new()
+    };
+    let security_headers: String = info.security_headers.iter()
+        .map(|(name, value)| {
+            let issue = host_info::header_issue(name, value.as_deref())
+                .map_or(String::new(), |issue| format!(" issue=\"{}\"", issue));
+            format!("            <header name=\"{}\"{}>{}</header>\n",
+                name, issue, xml_escape(value.as_deref().unwrap_or("")))
+        })
+        .collect();
+
+    format!("    <host url=\"{}\">
+        <http_version>{}</http_version>
+        <server>{}</server>
+{}{}        <security_headers>\n{}        </security_headers>
+    </host>\n",
+        xml_escape(host),
+        xml_escape(&info.http_version),
+        xml_escape(&info.server),
+        redirect,
+        certificate,
+        security_headers)
+}
+
+// Generates the headers element, which is left out when headers weren't captured
+fn xml_headers(headers: &[String]) -> String {
+    if headers.is_empty() { return String::new() }
+
+    let header_elements: String = headers.iter()
+        .map(|header| format!("        <header>{}</header>\n", xml_escape(header)))
+        .collect();
+    format!("    <headers>\n{}    </headers>\n", header_elements)
+}
+
+// Escapes a string for use in XML text or attributes
+pub fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
 }
 
 #[inline]
-pub fn output_json(response: &RequestResponse) -> String {
+pub fn output_json(response: &RequestResponse, tags: &[(String, String)]) -> String {
 
     format!("{{\
         \"url\": \"{}\", \
+        \"raw_url\": \"{}\", \
         \"code\": {}, \
+        \"http_version\": \"{}\", \
+        \"content_type\": \"{}\", \
+        \"server\": \"{}\", \
+        \"title\": \"{}\", \
         \"size\": {}, \
+        \"transfer_size\": {}, \
+        \"response_time\": {}, \
+        \"body_sha256\": {}, \
         \"is_directory\": {}, \
         \"is_listable\": {}, \
         \"found_from_listable\": {}, \
-        \"redirect_url\": \"{}\"\
+        \"redirect_url\": \"{}\", \
+        \"redirect_type\": {}, \
+        \"listable_entries\": {}, \
+        \"listable_size\": {}, \
+        \"verb_codes\": {{{}}}, \
+        \"bypasses\": {{{}}}, \
+        \"allowed_methods\": [{}], \
+        \"cors\": [{}], \
+        \"keywords\": [{}], \
+        \"framework\": {}, \
+        \"endpoint_hints\": [{}]{}{}\
         }}",
-        response.url,
+        json_escape(&response.url),
+        json_escape(&response.raw_url),
         response.code,
+        json_escape(&response.http_version),
+        json_escape(&response.content_type),
+        json_escape(&response.server),
+        json_escape(&response.title),
         response.content_len,
+        response.transfer_size,
+        response.response_time,
+        if response.body_sha256.is_empty() { String::from("null") } else { format!("\"{}\"", response.body_sha256) },
         response.is_directory,
         response.is_listable,
         response.found_from_listable,
-        response.redirect_url)
+        json_escape(&response.redirect_url),
+        redirect_type(response).map_or(String::from("null"), |redirect_type| format!("\"{}\"", redirect_type)),
+        response.listable_entries,
+        response.listable_size.map_or(String::from("null"), |size| size.to_string()),
+        response.verb_codes.iter()
+            .map(|(verb, code)| format!("\"{}\": {}", json_escape(verb), code))
+            .collect::<Vec<String>>()
+            .join(", "),
+        response.bypasses.iter()
+            .map(|(url, code)| format!("\"{}\": {}", json_escape(url), code))
+            .collect::<Vec<String>>()
+            .join(", "),
+        response.allowed_methods.iter()
+            .map(|method| format!("\"{}\"", json_escape(method)))
+            .collect::<Vec<String>>()
+            .join(", "),
+        response.cors.iter()
+            .map(|origin| format!("\"{}\"", json_escape(origin)))
+            .collect::<Vec<String>>()
+            .join(", "),
+        response.keywords.iter()
+            .map(|keyword| format!("\"{}\"", json_escape(keyword)))
+            .collect::<Vec<String>>()
+            .join(", "),
+        response.framework.as_ref().map_or(String::from("null"), |framework| format!("\"{}\"", json_escape(framework))),
+        response.endpoint_hints.iter()
+            .map(|hint| format!("\"{}\"", json_escape(hint)))
+            .collect::<Vec<String>>()
+            .join(", "),
+        json_headers(&response.headers),
+        json_tags(tags))
+}
+
+// Generates the headers field, which is left out when headers weren't captured
+fn json_headers(headers: &[String]) -> String {
+    if headers.is_empty() { return String::new() }
+
+    format!(", \"headers\": [{}]", headers.iter()
+        .map(|header| format!("\"{}\"", json_escape(header)))
+        .collect::<Vec<String>>()
+        .join(", "))
+}
+
+// Generates the tags field, which is left out when no tags were given
+fn json_tags(tags: &[(String, String)]) -> String {
+    if tags.is_empty() { return String::new() }
+
+    format!(", \"tags\": {{{}}}", tags.iter()
+        .map(|(name, value)| format!("\"{}\": \"{}\"", json_escape(name), json_escape(value)))
+        .collect::<Vec<String>>()
+        .join(", "))
+}
+
+// Formats the tags as name=value pairs separated by the separator
+pub fn format_tags(tags: &[(String, String)], separator: &str) -> String {
+    tags.iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+// Generates a JSON object announcing that a directory has finished being scanned
+#[inline]
+pub fn output_directory_event(stats: &DirectoryStats) -> String {
+    format!("{{\"event\": \"directory_complete\", \"url\": \"{}\", \"found\": {}, \"elapsed_ms\": {}}}",
+        json_escape(&stats.url),
+        stats.found,
+        stats.elapsed.as_millis())
+}
+
+// Generates a single line for the response with tab separated "Name: value"
+// fields in a fixed order, for use with grep, cut and awk
+#[inline]
+pub fn output_grep(response: &RequestResponse) -> String {
+    let redirect = if response.redirect_url.is_empty() { "-" } else { &response.redirect_url };
+    let source = if response.found_from_listable { "scraped" } else { "wordlist" };
+
+    let content_type = if response.content_type.is_empty() { "-" } else { media_type(&response.content_type) };
+    let server = if response.server.is_empty() { "-" } else { &response.server };
+    let title = if response.title.is_empty() { "-" } else { &response.title };
+
+    format!("Url: {}\tCode: {}\tSize: {}\tType: {}\tRedirect: {}\tSource: {}\tContent-Type: {}\tServer: {}\tTitle: {}\tRaw-Url: {}",
+        grep_escape(&response.url),
+        response.code,
+        response.content_len,
+        item_type(response),
+        grep_escape(redirect),
+        source,
+        grep_escape(content_type),
+        grep_escape(server),
+        grep_escape(title),
+        grep_escape(&response.raw_url))
+}
+
+// Removes characters which would break the line and field separators
+#[inline]
+fn grep_escape(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+// Describes the type of item the response is for
+#[inline]
+pub fn item_type(response: &RequestResponse) -> &'static str {
+    if response.is_listable { "Listable directory" }
+    else if response.is_directory { "Directory" }
+    else if response.found_from_listable { "Scraped" }
+    else { "File" }
+}
+
+pub const MARKDOWN_TABLE_HEADER: &str = "| Type | Path | Code | Size | Details |\n|---|---|---|---|---|";
+
+// Generates a row of a markdown table for the response
+#[inline]
+pub fn output_markdown(response: &RequestResponse) -> String {
+    let mut details = Vec::new();
+    match redirect_type(response) {
+        Some("internal") => details.push(format!("Redirects to {}", response.redirect_url)),
+        Some(redirect_type) => details.push(format!("Redirects to {} ({})", response.redirect_url, redirect_type)),
+        None => {}
+    }
+    if response.is_listable {
+        details.push(format!("{} files", response.listable_entries));
+        if let Some(size) = response.listable_size {
+            details.push(format!("~{} total", human_size(size)));
+        }
+    }
+    if !response.keywords.is_empty() {
+        details.push(format!("Contains {}", response.keywords.join(", ")));
+    }
+    if let Some(framework) = &response.framework {
+        details.push(format!("{} error page", framework));
+    }
+    if !response.endpoint_hints.is_empty() {
+        details.push(format!("Mentions {}", response.endpoint_hints.join(", ")));
+    }
+
+    let code = if response.code == 0 { String::from("-") } else { response.code.to_string() };
+
+    format!("| {} | {} | {} | {} | {} |",
+        item_type(response),
+        markdown_escape(&url_parse::path(&response.url)),
+        code,
+        response.content_len,
+        markdown_escape(&details.join(", ")))
+}
+
+// Escapes characters which would break a markdown table cell
+pub fn markdown_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|").replace('`', "\\`")
+}
+
+pub const BURP_HEADER: &str = "<?xml version=\"1.0\"?>
+<!DOCTYPE items [
+<!ELEMENT items (item*)>
+<!ATTLIST items burpVersion CDATA \"\">
+<!ATTLIST items exportTime CDATA \"\">
+<!ELEMENT item (time, url, host, port, protocol, method, path, extension, request, status, responselength, mimetype, response, comment)>
+<!ELEMENT time (#PCDATA)>
+<!ELEMENT url (#PCDATA)>
+<!ELEMENT host (#PCDATA)>
+<!ATTLIST host ip CDATA \"\">
+<!ELEMENT port (#PCDATA)>
+<!ELEMENT protocol (#PCDATA)>
+<!ELEMENT method (#PCDATA)>
+<!ELEMENT path (#PCDATA)>
+<!ELEMENT extension (#PCDATA)>
+<!ELEMENT request (#PCDATA)>
+<!ATTLIST request base64 (true|false) \"false\">
+<!ELEMENT status (#PCDATA)>
+<!ELEMENT responselength (#PCDATA)>
+<!ELEMENT mimetype (#PCDATA)>
+<!ELEMENT response (#PCDATA)>
+<!ATTLIST response base64 (true|false) \"false\">
+<!ELEMENT comment (#PCDATA)>
+]>
+<items burpVersion=\"\" exportTime=\"\">";
+
+// Generates an item in Burp Suite's site map format, containing
+// stubs of the request made and the response received
+pub fn output_burp(response: &RequestResponse) -> String {
+    let host = url_parse::host(&response.url);
+    let port = url_parse::port(&response.url);
+    let protocol = url_parse::scheme(&response.url);
+    let path = url_parse::path(&response.url);
+
+    let file_name = path.split('?').next().unwrap_or("").rsplit('/').next().unwrap_or("");
+    let extension = match file_name.rfind('.') {
+        Some(index) => xml_escape(&file_name[index+1..]),
+        None => String::from("null")
+    };
+
+    let request = format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n",
+        path, url_parse::authority(&response.url));
+
+    let mut response_stub = String::new();
+    if response.code != 0 {
+        response_stub = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", response.code, response.content_len);
+        if !response.redirect_url.is_empty() {
+            response_stub += &format!("Location: {}\r\n", response.redirect_url);
+        }
+        response_stub += "\r\n";
+    }
+
+    format!("  <item>
+    <time></time>
+    <url><![CDATA[{}]]></url>
+    <host ip=\"\">{}</host>
+    <port>{}</port>
+    <protocol>{}</protocol>
+    <method><![CDATA[GET]]></method>
+    <path><![CDATA[{}]]></path>
+    <extension>{}</extension>
+    <request base64=\"true\"><![CDATA[{}]]></request>
+    <status>{}</status>
+    <responselength>{}</responselength>
+    <mimetype></mimetype>
+    <response base64=\"true\"><![CDATA[{}]]></response>
+    <comment>{}</comment>
+  </item>\n",
+        response.url,
+        xml_escape(&host),
+        port,
+        protocol,
+        path,
+        extension,
+        base64_encode(request.as_bytes()),
+        if response.code == 0 { String::new() } else { response.code.to_string() },
+        response.content_len,
+        base64_encode(response_stub.as_bytes()),
+        item_type(response))
+}
+
+// Standard base64 encoding with padding
+pub fn base64_encode(data: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::new();
+
+    for chunk in data.chunks(3) {
+        let bytes = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let combined = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+
+        for position in 0..4 {
+            if position <= chunk.len() {
+                output.push(ALPHABET[(combined >> (18 - 6 * position) & 0x3f) as usize] as char);
+            }
+            else {
+                output.push('=');
+            }
+        }
+    }
+
+    output
+}
+
+// Generates a graphviz node for the response, coloured by the status code
+// Directories are drawn as folders and listable directories are outlined in bold
+pub fn output_dot(response: &RequestResponse, index: usize) -> String {
+    let path = url_parse::path(&response.url);
+    let name = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+    let name = if response.is_directory { format!("{}/", name) } else { String::from(name) };
+
+    let colour = match response.code {
+        200..=299 => "palegreen",
+        300..=399 => "lightblue",
+        400..=499 => "lightpink",
+        500..=599 => "khaki",
+        _ => "lightgrey"
+    };
+    let shape = if response.is_directory { "folder" } else { "note" };
+    let code = if response.code == 0 { String::from("scraped") } else { response.code.to_string() };
+
+    format!("n{} [label=\"{}\\n{}\", shape={}, fillcolor={}{}];",
+        index,
+        dot_escape(&name),
+        code,
+        shape,
+        colour,
+        if response.is_listable { ", penwidth=3, xlabel=\"listable\"" } else { "" })
+}
+
+// Escapes a string for use inside a quoted DOT identifier
+pub fn dot_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Escapes a string for use inside a JSON string literal
+pub fn json_escape(value: &str) -> String {
+    let mut output = String::new();
+    for c in value.chars() {
+        match c {
+            '"' => output += "\\\"",
+            '\\' => output += "\\\\",
+            '\n' => output += "\\n",
+            '\r' => output += "\\r",
+            '\t' => output += "\\t",
+            c if (c as u32) < 0x20 => output += &format!("\\u{:04x}", c as u32),
+            c => output.push(c)
+        }
+    }
+    output
+}
+
+// Paths containing any of these are reported as sensitive in SARIF output
+const SENSITIVE_PATHS: [&str; 12] = [
+    "/.git", "/.svn", "/.hg", "/.env", "/.htpasswd", "/.ds_store",
+    "/web.config", "/id_rsa", "backup", ".bak", ".old", ".sql"
+];
+
+// Rules which SARIF results can refer to: id, description and level
+const SARIF_RULES: [(&str, &str, &str); 9] = [
+    ("listable-directory", "Directory listing is enabled", "warning"),
+    ("sensitive-path", "Potentially sensitive file or directory", "warning"),
+    ("directory", "Directory discovered", "note"),
+    ("file", "File discovered", "note"),
+    ("scraped-file", "File discovered in a directory listing", "note"),
+    ("sensitive-keyword", "Response contains a sensitive keyword", "error"),
+    ("access-control-bypass", "Forbidden url is accessible with a modified path", "error"),
+    ("writable-resource", "Resource allows methods which can change it", "warning"),
+    ("permissive-cors", "CORS policy trusts an arbitrary origin", "warning")
+];
+
+// Methods which let a resource be changed, highlighted when a url allows them
+const WRITE_METHODS: [&str; 3] = ["PUT", "DELETE", "PATCH"];
+
+// Returns the index into SARIF_RULES of the rule the response falls under
+fn sarif_rule(response: &RequestResponse) -> usize {
+    let lowercase_url = response.url.to_lowercase();
+    if !response.bypasses.is_empty() { 6 }
+    else if !response.keywords.is_empty() { 5 }
+    else if !response.cors.is_empty() { 8 }
+    else if response.allowed_methods.iter().any(|method| WRITE_METHODS.contains(&method.as_str())) { 7 }
+    else if response.is_listable { 0 }
+    else if SENSITIVE_PATHS.iter().any(|path| lowercase_url.contains(path)) { 1 }
+    else if response.is_directory { 2 }
+    else if response.found_from_listable { 4 }
+    else { 3 }
+}
+
+// How serious a finding is, following the level of its SARIF rule
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+pub enum Severity {
+    Low,
+    Medium,
+    High
+}
+
+impl Severity {
+    pub fn from_str(severity: &str) -> Severity {
+        match severity {
+            "high" => Severity::High,
+            "medium" => Severity::Medium,
+            _ => Severity::Low
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high"
+        }
+    }
+}
+
+pub fn severity(response: &RequestResponse) -> Severity {
+    match SARIF_RULES[sarif_rule(response)].2 {
+        "error" => Severity::High,
+        "warning" => Severity::Medium,
+        _ => Severity::Low
+    }
+}
+
+// Generates a SARIF 2.1.0 log containing a result for each response
+pub fn output_sarif(responses: &[RequestResponse], tags: &[(String, String)]) -> String {
+    let rules: Vec<String> = SARIF_RULES.iter()
+        .map(|(id, description, level)| format!(
+            "{{\"id\": \"{}\", \"shortDescription\": {{\"text\": \"{}\"}}, \
+            \"defaultConfiguration\": {{\"level\": \"{}\"}}}}",
+            id, description, level))
+        .collect();
+
+    let results: Vec<String> = responses.iter()
+        .map(|response| {
+            let rule_index = sarif_rule(response);
+            let (id, description, level) = SARIF_RULES[rule_index];
+            format!(
+                "{{\"ruleId\": \"{}\", \"ruleIndex\": {}, \"level\": \"{}\", \
+                \"message\": {{\"text\": \"{}: {} {}\"}}, \
+                \"locations\": [{{\"physicalLocation\": {{\"artifactLocation\": {{\"uri\": \"{}\"}}}}}}]}}",
+                id, rule_index, level, description,
+                json_escape(&response.url), json_escape(&output_suffix(response, false)),
+                json_escape(&response.url))
+        })
+        .collect();
+
+    format!("{{\"$schema\": \"https://json.schemastore.org/sarif-2.1.0.json\", \
+        \"version\": \"2.1.0\", \
+        \"runs\": [{{\"tool\": {{\"driver\": {{\"name\": \"Dirble\", \"version\": \"{}\", \
+        \"informationUri\": \"https://github.com/nccgroup/dirble\", \"rules\": [{}]}}}}, \
+        \"results\": [{}]{}}}]}}",
+        env!("CARGO_PKG_VERSION"),
+        rules.join(", "),
+        results.join(",\n"),
+        sarif_properties(tags))
+}
+
+// Generates the run's property bag, with each tag as a name=value string
+fn sarif_properties(tags: &[(String, String)]) -> String {
+    if tags.is_empty() { return String::new() }
+
+    format!(", \"properties\": {{\"tags\": [{}]}}", tags.iter()
+        .map(|(name, value)| format!("\"{}\"", json_escape(&format!("{}={}", name, value))))
+        .collect::<Vec<String>>()
+        .join(", "))
+}
+
+// Returns a description of why the response matches the JUnit failure rule, if it does
+// Rules are the SARIF rule ids or "code:" followed by a response code
+pub fn junit_failure(response: &RequestResponse, rule: &str) -> Option<String> {
+    let matches = match rule {
+        "listable-directory" => response.is_listable,
+        "sensitive-path" => {
+            let lowercase_url = response.url.to_lowercase();
+            SENSITIVE_PATHS.iter().any(|path| lowercase_url.contains(path))
+        },
+        "sensitive-keyword" => !response.keywords.is_empty(),
+        "access-control-bypass" => !response.bypasses.is_empty(),
+        "writable-resource" => response.allowed_methods.iter()
+            .any(|method| WRITE_METHODS.contains(&method.as_str())),
+        "permissive-cors" => !response.cors.is_empty(),
+        "directory" => response.is_directory,
+        "file" => !response.is_directory && !response.found_from_listable,
+        "scraped-file" => response.found_from_listable,
+        _ => return rule.strip_prefix("code:")
+            .filter(|code| code.parse::<u32>() == Ok(response.code))
+            .map(|code| format!("Responded with code {}", code))
+    };
+
+    if !matches { return None }
+    SARIF_RULES.iter()
+        .find(|(id, _, _)| *id == rule)
+        .map(|(_, description, _)| String::from(*description))
+}
+
+// Returns whether the given string is a valid JUnit failure rule
+pub fn is_junit_rule(rule: &str) -> bool {
+    SARIF_RULES.iter().any(|(id, _, _)| *id == rule) ||
+        rule.strip_prefix("code:").is_some_and(|code| code.parse::<u32>().is_ok())
+}
+
+// Generates a JUnit XML report with a test case for each response, grouped into
+// a test suite per host. Responses which match any of the rules are failures
+pub fn output_junit(responses: &[RequestResponse], rules: &[String]) -> String {
+    let mut suites: BTreeMap<String, (Vec<String>, usize)> = BTreeMap::new();
+    let mut total_failures = 0;
+
+    for response in responses {
+        let (cases, failures) = suites.entry(url_parse::base_url(&response.url)).or_default();
+
+        let failure_messages: Vec<(String, String)> = rules.iter()
+            .filter_map(|rule| junit_failure(response, rule).map(|message| (rule.clone(), message)))
+            .collect();
+
+        let mut case = format!("    <testcase classname=\"{}\" name=\"{}\"",
+            xml_escape(&url_parse::base_url(&response.url)), xml_escape(&url_parse::path(&response.url)));
+        if failure_messages.is_empty() {
+            case += "/>";
+        }
+        else {
+            case += ">\n";
+            for (rule, message) in &failure_messages {
+                case += &format!("      <failure type=\"{}\" message=\"{}\">{}</failure>\n",
+                    xml_escape(rule), xml_escape(message),
+                    xml_escape(&format!("{}{}", output_url(response), output_suffix(response, false))));
+            }
+            case += "    </testcase>";
+            *failures += 1;
+            total_failures += 1;
+        }
+        cases.push(case);
+    }
+
+    let suites: Vec<String> = suites.iter()
+        .map(|(name, (cases, failures))| format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n{}\n  </testsuite>",
+            xml_escape(name), cases.len(), failures, cases.join("\n")))
+        .collect();
+
+    format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <testsuites name=\"Dirble\" tests=\"{}\" failures=\"{}\">\n{}\n</testsuites>\n",
+        responses.len(), total_failures, suites.join("\n"))
+}
+
+// Formats seconds since the unix epoch as an ISO 8601 UTC timestamp
+pub fn format_timestamp(seconds: u64) -> String
+{
+    let (year, month, day) = dates::civil_from_days((seconds / 86400) as i64);
+    let time = seconds % 86400;
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+pub const CSV_HEADER: &str = "url,code,size,is_directory,is_listable,redirect_url,content_type,server,body_sha256,transfer_size,response_time,title,raw_url";
+
+#[inline]
+pub fn output_csv(response: &RequestResponse) -> String {
+    format!("{},{},{},{},{},{},{},{},{},{},{},{},{}",
+        csv_escape(&response.url),
+        response.code,
+        response.content_len,
+        response.is_directory,
+        response.is_listable,
+        csv_escape(&response.redirect_url),
+        csv_escape(&response.content_type),
+        csv_escape(&response.server),
+        response.body_sha256,
+        response.transfer_size,
+        response.response_time,
+        csv_escape(&response.title),
+        csv_escape(&response.raw_url))
+}
+
+// Quotes a CSV field if it contains a separator, quote or newline
+pub fn csv_escape(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace("\"", "\"\""))
+    }
+    else {
+        String::from(field)
+    }
 }
 
 #[cfg(test)]
@@ -138,26 +943,192 @@ mod tests {
             url: "http://example.com".into(),
             code: 200,
             content_len: 350,
+            transfer_size: 120,
             is_directory: false,
             is_listable: true,
             found_from_listable: false,
             redirect_url: "https://example.org".into(),
-            parent_depth: 0
+            parent_depth: 0,
+            listable_entries: 12,
+            listable_size: None,
+            raw_url: "http://example.com/%61".into(),
+            http_version: "HTTP/2".into(),
+            content_type: "text/html; charset=utf-8".into(),
+            ..Default::default()
         };
-        let json = super::output_json(&req_response);
+        let json = super::output_json(&req_response, &[]);
 
         assert_eq!(
             json,
             "{\
             \"url\": \"http://example.com\", \
+            \"raw_url\": \"http://example.com/%61\", \
             \"code\": 200, \
+            \"http_version\": \"HTTP/2\", \
+            \"content_type\": \"text/html; charset=utf-8\", \
+            \"server\": \"\", \
+            \"title\": \"\", \
             \"size\": 350, \
+            \"transfer_size\": 120, \
+            \"response_time\": 0, \
+            \"body_sha256\": null, \
             \"is_directory\": false, \
             \"is_listable\": true, \
             \"found_from_listable\": false, \
-            \"redirect_url\": \"https://example.org\"\
+            \"redirect_url\": \"https://example.org\", \
+            \"redirect_type\": \"external\", \
+            \"listable_entries\": 12, \
+            \"listable_size\": null, \
+            \"verb_codes\": {}, \
+            \"bypasses\": {}, \
+            \"allowed_methods\": [], \
+            \"cors\": [], \
+            \"keywords\": [], \
+            \"framework\": null, \
+            \"endpoint_hints\": []\
             }\
             ",
             "JSON output appears invalid!");
+
+        let req_response = super::RequestResponse {
+            headers: vec!["HTTP/1.1 200 OK".into(), "Server: \"test\"".into()],
+            ..req_response
+        };
+        let tags = vec![("engagement".into(), "acme".into())];
+        assert!(super::output_json(&req_response, &tags).ends_with(
+            "\"endpoint_hints\": [], \"headers\": [\"HTTP/1.1 200 OK\", \"Server: \\\"test\\\"\"], \
+            \"tags\": {\"engagement\": \"acme\"}}"));
+    }
+
+    #[test]
+    fn check_csv_format() {
+        let req_response = super::RequestResponse {
+            url: "http://example.com/a,b".into(),
+            code: 301,
+            content_len: 0,
+            is_directory: true,
+            is_listable: false,
+            found_from_listable: false,
+            redirect_url: "http://example.com/a,b/".into(),
+            parent_depth: 0,
+            ..Default::default()
+        };
+        let csv = super::output_csv(&req_response);
+
+        assert_eq!(
+            csv,
+            "\"http://example.com/a,b\",301,0,true,false,\"http://example.com/a,b/\",,,,0,0,,",
+            "CSV output appears invalid!");
+    }
+
+    #[test]
+    fn check_sarif_rules() {
+        let mut req_response = super::RequestResponse {
+            url: "http://example.com/files/".into(),
+            code: 200,
+            content_len: 100,
+            is_directory: true,
+            is_listable: true,
+            found_from_listable: false,
+            redirect_url: "".into(),
+            parent_depth: 0,
+            listable_entries: 3,
+            listable_size: None,
+            ..Default::default()
+        };
+        assert_eq!(super::SARIF_RULES[super::sarif_rule(&req_response)].0, "listable-directory");
+        assert!(super::severity(&req_response) == super::Severity::Medium);
+
+        req_response.is_listable = false;
+        req_response.url = "http://example.com/.git/".into();
+        assert_eq!(super::SARIF_RULES[super::sarif_rule(&req_response)].0, "sensitive-path");
+
+        req_response.is_directory = false;
+        req_response.url = "http://example.com/index.html".into();
+        assert_eq!(super::SARIF_RULES[super::sarif_rule(&req_response)].0, "file");
+        assert!(super::severity(&req_response) == super::Severity::Low);
+
+        req_response.keywords = vec!["password".into()];
+        assert_eq!(super::SARIF_RULES[super::sarif_rule(&req_response)].0, "sensitive-keyword");
+        assert!(super::severity(&req_response) == super::Severity::High);
+
+        req_response.bypasses = vec![("http://example.com/%2e/index.html".into(), 200)];
+        assert_eq!(super::SARIF_RULES[super::sarif_rule(&req_response)].0, "access-control-bypass");
+
+        req_response.keywords.clear();
+        req_response.bypasses.clear();
+        req_response.allowed_methods = vec!["GET".into(), "PUT".into()];
+        assert_eq!(super::SARIF_RULES[super::sarif_rule(&req_response)].0, "writable-resource");
+
+        req_response.cors = vec!["null".into()];
+        assert_eq!(super::SARIF_RULES[super::sarif_rule(&req_response)].0, "permissive-cors");
+    }
+
+    #[test]
+    fn check_junit_rules() {
+        let req_response = super::RequestResponse {
+            url: "http://example.com/backup/".into(),
+            code: 403,
+            is_directory: true,
+            ..Default::default()
+        };
+
+        assert!(super::junit_failure(&req_response, "sensitive-path").is_some());
+        assert!(super::junit_failure(&req_response, "code:403").is_some());
+        assert!(super::junit_failure(&req_response, "code:200").is_none());
+        assert!(super::junit_failure(&req_response, "listable-directory").is_none());
+        assert!(super::junit_failure(&req_response, "access-control-bypass").is_none());
+        assert!(super::is_junit_rule("code:500"));
+        assert!(!super::is_junit_rule("code:abc"));
+
+        let req_response = super::RequestResponse {
+            bypasses: vec![("http://example.com/%2e/backup/".into(), 200)],
+            ..req_response
+        };
+        assert!(super::junit_failure(&req_response, "access-control-bypass").is_some());
+
+        let req_response = super::RequestResponse {
+            allowed_methods: vec!["GET".into(), "OPTIONS".into()],
+            ..req_response
+        };
+        assert!(super::junit_failure(&req_response, "writable-resource").is_none());
+
+        let req_response = super::RequestResponse {
+            allowed_methods: vec!["GET".into(), "PUT".into()],
+            ..req_response
+        };
+        assert!(super::junit_failure(&req_response, "writable-resource").is_some());
+        assert!(super::junit_failure(&req_response, "permissive-cors").is_none());
+
+        let req_response = super::RequestResponse {
+            cors: vec!["null".into()],
+            ..req_response
+        };
+        assert!(super::junit_failure(&req_response, "permissive-cors").is_some());
+    }
+
+    #[test]
+    fn check_format_timestamp() {
+        assert_eq!(super::format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(super::format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(super::format_timestamp(1_792_152_000), "2026-10-16T12:00:00Z");
+    }
+
+    #[test]
+    fn check_media_type_matches() {
+        assert!(super::media_type_matches("application/json", "application/json; charset=utf-8"));
+        assert!(super::media_type_matches("image/*", "image/png"));
+        assert!(super::media_type_matches("Image", "image/svg+xml"));
+        assert!(!super::media_type_matches("image/*", "text/html"));
+        assert!(!super::media_type_matches("application/json", ""));
+    }
+
+    #[test]
+    fn check_base64() {
+        assert_eq!(super::base64_encode(b""), "");
+        assert_eq!(super::base64_encode(b"f"), "Zg==");
+        assert_eq!(super::base64_encode(b"fo"), "Zm8=");
+        assert_eq!(super::base64_encode(b"foo"), "Zm9v");
+        assert_eq!(super::base64_encode(b"foobar"), "Zm9vYmFy");
     }
 }
\ No newline at end of file
@@ -0,0 +1,117 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    env,
+    fs::{self, File},
+    io,
+    time::{SystemTime, UNIX_EPOCH}
+};
+use sha2::{Digest, Sha256};
+use crate::arg_parse::GlobalOpts;
+use crate::hash::hex;
+use crate::output_bodies;
+use crate::output_format::{self, json_escape};
+
+// Writes a JSON manifest containing the SHA-256 hash of the command line
+// the scan was run with, of each file it read and of each output file, so
+// that the results can be checked for tampering later
+// Must be called after every output file has been closed
+pub fn write_manifest(filename: &str, global_opts: &GlobalOpts)
+{
+    let mut config_hasher = Sha256::new();
+    for arg in env::args() {
+        config_hasher.update(arg.as_bytes());
+        config_hasher.update([0]);
+    }
+
+    let manifest = format!("{{\n  \"tool\": \"Dirble\",\n  \"version\": \"{}\",\n  \"completed\": \"{}\",\n  \
+        \"config_sha256\": \"{}\",\n  \"inputs\": [{}],\n  \"outputs\": [{}]\n}}\n",
+        env!("CARGO_PKG_VERSION"),
+        output_format::format_timestamp(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()),
+        hex(&config_hasher.finalize()),
+        file_entries(&input_files(global_opts)),
+        file_entries(&output_files(global_opts)));
+
+    if let Err(e) = fs::write(filename, manifest) {
+        println!("Couldn't write manifest {}: {}", filename, e);
+    }
+}
+
+// Returns the name of each wordlist and other file the scan has read
+fn input_files(global_opts: &GlobalOpts) -> Vec<String>
+{
+    let mut input_files = global_opts.wordlist_files.clone();
+    input_files.extend(global_opts.technologies.iter()
+        .filter_map(|technology| technology.wordlist_file.clone()));
+    input_files.extend(global_opts.input_files.iter().cloned());
+    input_files
+}
+
+// Returns the name of each file the scan has written results to
+fn output_files(global_opts: &GlobalOpts) -> Vec<String>
+{
+    let files = [
+        &global_opts.output_file, &global_opts.json_file, &global_opts.xml_file,
+        &global_opts.csv_file, &global_opts.jsonl_file, &global_opts.grep_file,
+        &global_opts.sarif_file, &global_opts.junit_file, &global_opts.markdown_file,
+        &global_opts.sqlite_file, &global_opts.burp_file, &global_opts.dot_file
+    ];
+
+    let mut output_files: Vec<String> = files.iter()
+        .filter_map(|file| (*file).clone())
+        .collect();
+    if let Some((_, filename)) = &global_opts.output_template {
+        output_files.push(filename.clone());
+    }
+    if let Some(filename) = &global_opts.discovered_hosts_file {
+        output_files.push(filename.clone());
+    }
+    if let Some(directory) = &global_opts.save_bodies {
+        output_files.extend(output_bodies::saved_files(directory));
+    }
+    output_files
+}
+
+// Generates the JSON objects listing the path, size and hash of each file
+fn file_entries(filenames: &[String]) -> String
+{
+    // Input read from stdin can't be read again to be hashed
+    let entries: Vec<String> = filenames.iter()
+        .filter(|filename| *filename != "-")
+        .filter_map(|filename| match hash_file(filename) {
+            Ok((size, hash)) => Some(format!(
+                "\n    {{\"path\": \"{}\", \"size\": {}, \"sha256\": \"{}\"}}",
+                json_escape(filename), size, hash)),
+            Err(e) => {
+                println!("Couldn't hash {} for the manifest: {}", filename, e);
+                None
+            }
+        })
+        .collect();
+
+    if entries.is_empty() { String::new() } else { entries.join(",") + "\n  " }
+}
+
+// Returns the size and SHA-256 hash of the file
+fn hash_file(filename: &str) -> io::Result<(u64, String)>
+{
+    let mut file = File::open(filename)?;
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut file, &mut hasher)?;
+    Ok((size, hex(&hasher.finalize())))
+}
@@ -0,0 +1,133 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    process::exit,
+    time::{SystemTime, UNIX_EPOCH}
+};
+use rusqlite::{Connection, params};
+use crate::output::{self, OutputWriter, WriterMessage};
+use crate::request::RequestResponse;
+
+// Number of results inserted in each transaction
+const BATCH_SIZE: usize = 1000;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS results (
+        id INTEGER PRIMARY KEY,
+        scan_time INTEGER NOT NULL,
+        url TEXT NOT NULL,
+        code INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        is_directory INTEGER NOT NULL,
+        is_listable INTEGER NOT NULL,
+        found_from_listable INTEGER NOT NULL,
+        redirect_url TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS results_code ON results (code);
+    CREATE INDEX IF NOT EXISTS results_url ON results (url);
+    CREATE TABLE IF NOT EXISTS findings (
+        url TEXT PRIMARY KEY,
+        first_seen INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL,
+        times_seen INTEGER NOT NULL,
+        last_code INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS scan_tags (
+        scan_time INTEGER NOT NULL,
+        name TEXT NOT NULL,
+        value TEXT NOT NULL
+    );";
+
+// Opens the database and starts a writer thread which inserts each
+// response into the results table as soon as it is received
+// The findings table tracks when each url was first and last seen
+// across every scan written to the same database, and scan_tags holds the
+// tags each scan was run with so results can be filtered by them
+pub fn spawn_writer(filename: &str, tags: &[(String, String)]) -> OutputWriter
+{
+    let connection = open_database(filename);
+    let scan_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    for (name, value) in tags {
+        connection.execute("INSERT INTO scan_tags (scan_time, name, value) VALUES (?1, ?2, ?3)",
+            params![scan_time, name, value]).unwrap();
+    }
+    let mut pending = 0;
+
+    output::start_writer(true, move |message| {
+        match message {
+            WriterMessage::Response(response) => {
+                if pending == 0 {
+                    connection.execute_batch("BEGIN").unwrap();
+                }
+                insert_response(&connection, &response, scan_time);
+
+                pending += 1;
+                if pending >= BATCH_SIZE {
+                    connection.execute_batch("COMMIT").unwrap();
+                    pending = 0;
+                }
+            },
+            // Commit whenever a directory finishes so that readers see completed subtrees,
+            // the report is sent when the scan finishes
+            WriterMessage::DirectoryComplete(_) | WriterMessage::Report(_) => {
+                if pending > 0 {
+                    connection.execute_batch("COMMIT").unwrap();
+                    pending = 0;
+                }
+            }
+        }
+    })
+}
+
+// Opens the given database, creating the results table if it doesn't exist
+fn open_database(filename: &str) -> Connection
+{
+    let connection = Connection::open(filename)
+        .unwrap_or_else(|error| { println!("Opening database \"{}\" failed: {}", filename, error); exit(2); });
+    connection.execute_batch(SCHEMA)
+        .unwrap_or_else(|error| { println!("Creating tables in \"{}\" failed: {}", filename, error); exit(2); });
+
+    connection
+}
+
+fn insert_response(connection: &Connection, response: &RequestResponse, scan_time: i64)
+{
+    connection.execute(
+        "INSERT INTO results (scan_time, url, code, size, is_directory, is_listable,
+            found_from_listable, redirect_url) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            scan_time,
+            response.url,
+            response.code,
+            response.content_len as i64,
+            response.is_directory,
+            response.is_listable,
+            response.found_from_listable,
+            response.redirect_url
+        ]).unwrap();
+
+    // Only count a url once per scan, even if it was found more than once
+    connection.execute(
+        "INSERT INTO findings (url, first_seen, last_seen, times_seen, last_code)
+            VALUES (?1, ?2, ?2, 1, ?3)
+            ON CONFLICT (url) DO UPDATE SET
+                times_seen = times_seen + (last_seen != excluded.last_seen),
+                last_seen = excluded.last_seen,
+                last_code = excluded.last_code",
+        params![response.url, scan_time, response.code]).unwrap();
+}
@@ -0,0 +1,112 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fs,
+    io::Write,
+    process::exit
+};
+use serde::Serialize;
+use tera::{Context, Tera};
+use crate::output::{self, OutputWriter, WriterMessage};
+use crate::request::RequestResponse;
+
+const TEMPLATE_NAME: &str = "output";
+
+// Totals for the scan which are made available to templates as "summary"
+#[derive(Serialize)]
+struct Summary {
+    total: usize,
+    directories: usize,
+    files: usize,
+    listable: usize,
+    scraped: usize,
+    codes: BTreeMap<u32, usize>
+}
+
+// Loads the template and starts a writer thread which renders it
+// once the scan is finished. The template is given the sorted list
+// of responses as "responses", the totals as "summary" and the tags the
+// scan was run with as "tags"
+pub fn spawn_writer(template_file: &str, filename: &String, tags: &[(String, String)]) -> OutputWriter
+{
+    let template = match fs::read_to_string(template_file) {
+        Ok(template) => template,
+        Err(e) => {
+            println!("Couldn't read template {}: {}", template_file, e);
+            exit(2);
+        }
+    };
+
+    let mut tera = Tera::default();
+    if let Err(e) = tera.add_raw_template(TEMPLATE_NAME, &template) {
+        println!("Couldn't parse template {}: {}", template_file, error_chain(&e));
+        exit(2);
+    }
+
+    let mut handle = output::generate_handle(filename);
+    let template_file = String::from(template_file);
+    let tags: BTreeMap<String, String> = tags.iter().cloned().collect();
+
+    output::start_writer(false, move |message| {
+        if let WriterMessage::Report(responses) = message {
+            let mut context = Context::new();
+            context.insert("responses", responses.as_ref());
+            context.insert("summary", &summarise(&responses));
+            context.insert("tags", &tags);
+
+            match tera.render(TEMPLATE_NAME, &context) {
+                Ok(rendered) => {
+                    handle.write_all(rendered.as_bytes()).unwrap();
+                    handle.flush().unwrap();
+                },
+                Err(e) => println!("Couldn't render template {}: {}", template_file, error_chain(&e))
+            }
+        }
+    })
+}
+
+fn summarise(responses: &[RequestResponse]) -> Summary
+{
+    let mut codes = BTreeMap::new();
+    for response in responses {
+        *codes.entry(response.code).or_insert(0) += 1;
+    }
+
+    Summary {
+        total: responses.len(),
+        directories: responses.iter().filter(|response| response.is_directory).count(),
+        files: responses.iter().filter(|response| !response.is_directory).count(),
+        listable: responses.iter().filter(|response| response.is_listable).count(),
+        scraped: responses.iter().filter(|response| response.found_from_listable).count(),
+        codes
+    }
+}
+
+// Tera puts the useful detail of an error in its sources
+fn error_chain(error: &tera::Error) -> String
+{
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        message = format!("{}\n{}", message, e);
+        source = e.source();
+    }
+    message
+}
@@ -0,0 +1,167 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs},
+    sync::{Arc, Mutex, mpsc::{self, Receiver, Sender}},
+    thread,
+    time::Duration
+};
+use crate::arg_parse::GlobalOpts;
+use crate::request;
+use crate::url_parse;
+
+// Ports probed on bare targets when none are given
+pub const DEFAULT_PORTS: &str = "80,443,8080,8443";
+
+// Largest IPv4 range which can be given, a /16
+const MAX_RANGE_SIZE: u32 = 65536;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Expands a bare target into the hosts it covers: a hostname or address is
+// returned as is and an IPv4 CIDR range is expanded to each address in it
+pub fn expand_target(target: &str) -> Result<Vec<String>, String> {
+    let target = target.trim();
+    let (address, prefix) = match target.split_once('/') {
+        Some(parts) => parts,
+        None if target.is_empty() => return Err(String::from("empty target")),
+        None => return Ok(vec![String::from(target)])
+    };
+
+    let address: Ipv4Addr = address.parse()
+        .map_err(|_| format!("{} isn't an IPv4 CIDR range", target))?;
+    let prefix: u32 = match prefix.parse() {
+        Ok(prefix) if prefix <= 32 => prefix,
+        _ => return Err(format!("{} has an invalid prefix length", target))
+    };
+    let size = 1u64 << (32 - prefix);
+    if size > MAX_RANGE_SIZE as u64 {
+        return Err(format!("{} is larger than a /16", target))
+    }
+
+    let network = u32::from(address) & !((size - 1) as u32);
+    Ok((0..size as u32).map(|offset| Ipv4Addr::from(network + offset).to_string()).collect())
+}
+
+// Starts a pool of workers which resolve each target, probe it on each of
+// the ports and send the url of every HTTP service they confirm
+// The receiver disconnects once every target has been probed, so that the
+// scan can start on the first services while the rest are still probed
+pub fn start(targets: &[String], ports: &[u16], threads: u32, global_opts: Arc<GlobalOpts>) -> Receiver<String> {
+    let queue: VecDeque<(String, u16)> = targets.iter()
+        .flat_map(|target| ports.iter().map(move |port| (target.clone(), *port)))
+        .collect();
+    let queue = Arc::new(Mutex::new(queue));
+    let (tx, rx) = mpsc::channel();
+
+    for _ in 0..threads {
+        let queue = queue.clone();
+        let tx = tx.clone();
+        let global_opts = global_opts.clone();
+        thread::spawn(move || worker(queue, tx, global_opts));
+    }
+
+    rx
+}
+
+fn worker(queue: Arc<Mutex<VecDeque<(String, u16)>>>, tx: Sender<String>, global_opts: Arc<GlobalOpts>) {
+    // Connecting directly would go around the proxy or the interface the
+    // user chose, so in those cases every port is left for curl to try
+    let proxied = (global_opts.proxy_enabled && !global_opts.proxy_address.is_empty())
+        || global_opts.interface.is_some();
+
+    // Services are confirmed even if their certificates aren't valid, the
+    // scan itself reports the certificate errors unless they're ignored
+    let mut easy = request::generate_easy(global_opts.clone());
+    easy.connect_timeout(CONNECT_TIMEOUT).unwrap();
+    easy.ssl_verify_host(false).unwrap();
+    easy.ssl_verify_peer(false).unwrap();
+
+    loop {
+        let next = queue.lock().unwrap().pop_front();
+        let (target, port) = match next {
+            Some(next) => next,
+            None => return
+        };
+
+        if !proxied && !port_open(&target, port) { continue }
+
+        // Try the scheme usually found on the port first
+        let schemes = if port == 443 || port == 8443 { ["https", "http"] } else { ["http", "https"] };
+        for scheme in schemes {
+            let url = service_url(scheme, &target, port);
+            easy.certinfo(scheme == "https").unwrap();
+            if request::perform(&mut easy, &url).is_ok() {
+                // The response is to the service's root, so it's kept for
+                // the report rather than requested again
+                let base_url = url_parse::base_url(&url);
+                if global_opts.scanned_hosts.claim(&base_url) {
+                    global_opts.scanned_hosts.record(&base_url, request::last_host_info(&easy, &base_url));
+                }
+                if tx.send(url).is_err() { return }
+                break;
+            }
+        }
+    }
+}
+
+fn port_open(target: &str, port: u16) -> bool {
+    let host = target.trim_start_matches('[').trim_end_matches(']');
+    let addresses: Vec<SocketAddr> = match host.parse::<IpAddr>() {
+        Ok(address) => vec![SocketAddr::new(address, port)],
+        Err(_) => match (host, port).to_socket_addrs() {
+            Ok(addresses) => addresses.collect(),
+            Err(_) => return false
+        }
+    };
+
+    addresses.first()
+        .is_some_and(|address| TcpStream::connect_timeout(address, CONNECT_TIMEOUT).is_ok())
+}
+
+// Generates the root url of the service, leaving out default ports
+fn service_url(scheme: &str, target: &str, port: u16) -> String {
+    let host = if target.contains(':') && !target.starts_with('[') {
+        format!("[{}]", target)
+    }
+    else {
+        String::from(target)
+    };
+
+    if (scheme == "http" && port == 80) || (scheme == "https" && port == 443) {
+        format!("{}://{}/", scheme, host)
+    }
+    else {
+        format!("{}://{}:{}/", scheme, host, port)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_expand_target() {
+        assert_eq!(super::expand_target("example.com"), Ok(vec![String::from("example.com")]));
+        assert_eq!(super::expand_target("10.0.0.5/30"),
+            Ok(vec!["10.0.0.4".into(), "10.0.0.5".into(), "10.0.0.6".into(), "10.0.0.7".into()]));
+        assert_eq!(super::expand_target("10.0.0.0/16").unwrap().len(), 65536);
+        assert!(super::expand_target("10.0.0.0/8").is_err());
+        assert!(super::expand_target("10.0.0.0/33").is_err());
+        assert!(super::expand_target("example.com/24").is_err());
+    }
+}
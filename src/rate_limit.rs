@@ -0,0 +1,190 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant}
+};
+use crate::tui;
+
+// Limits the requests made by every thread together to a number per second
+// Each request is given the next free slot, so requests are spread evenly
+// rather than sent in bursts at the start of each second
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>
+}
+
+impl RateLimiter {
+    pub fn new(requests_per_second: u32) -> RateLimiter {
+        RateLimiter {
+            interval: Duration::from_secs(1) / requests_per_second,
+            next_slot: Mutex::new(Instant::now())
+        }
+    }
+
+    // Blocks until the calling thread is allowed to make a request
+    pub fn wait(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+
+        let now = Instant::now();
+        if slot > now {
+            thread::sleep(slot - now);
+        }
+    }
+}
+
+// Shortest delay used when a host first starts rejecting requests
+const MIN_HOST_DELAY: Duration = Duration::from_millis(100);
+// Longest delay a host can be slowed to, also caps large Retry-After waits
+const MAX_HOST_DELAY: Duration = Duration::from_secs(60);
+// Number of normal responses in a row before the delay is halved
+const RECOVERY_RESPONSES: u32 = 10;
+
+#[derive(Default)]
+struct HostState {
+    delay: Duration,
+    next_slot: Option<Instant>,
+    // When the delay was last increased
+    slowed: Option<Instant>,
+    normal_responses: u32
+}
+
+// Slows requests to hosts which respond with 429, or 503 with a
+// Retry-After, and speeds them back up once they respond normally again
+// Hosts which never push back aren't slowed at all
+#[derive(Default)]
+pub struct HostThrottle {
+    hosts: Mutex<HashMap<String, HostState>>
+}
+
+impl HostThrottle {
+    pub fn new() -> HostThrottle {
+        HostThrottle::default()
+    }
+
+    // Blocks until the calling thread is allowed to make a request to the
+    // host, giving each request to a slowed host the next free slot
+    pub fn wait(&self, host: &str) {
+        let slot = {
+            let mut hosts = self.hosts.lock().unwrap();
+            match hosts.get_mut(host) {
+                Some(state) if state.delay > Duration::ZERO => {
+                    let slot = state.next_slot.map_or(Instant::now(), |next_slot| next_slot.max(Instant::now()));
+                    state.next_slot = Some(slot + state.delay);
+                    slot
+                },
+                _ => return
+            }
+        };
+
+        let now = Instant::now();
+        if slot > now {
+            thread::sleep(slot - now);
+        }
+    }
+
+    // Adjusts the delay for the host after a response, returning true if the
+    // host asked for requests to be slowed so the request should be retried
+    pub fn add_response(&self, host: &str, code: u32, retry_after: Option<Duration>) -> bool {
+        self.add_response_at(host, code, retry_after, Instant::now())
+    }
+
+    fn add_response_at(&self, host: &str, code: u32, retry_after: Option<Duration>, now: Instant) -> bool {
+        let mut hosts = self.hosts.lock().unwrap();
+        if code == 429 || (code == 503 && retry_after.is_some()) {
+            let state = hosts.entry(String::from(host)).or_default();
+            // Requests already in flight when the host was slowed don't slow
+            // it down any further
+            if state.slowed.is_none_or(|slowed| now.duration_since(slowed) >= state.delay) {
+                state.delay = (state.delay * 2).clamp(MIN_HOST_DELAY, MAX_HOST_DELAY);
+                state.slowed = Some(now);
+                tui::message(format!("Slowing requests to {} to one every {}ms after a {} response",
+                    host, state.delay.as_millis(), code));
+            }
+            state.normal_responses = 0;
+            // Nothing else is sent to the host until the wait it asked for
+            // is over
+            let resume = now + retry_after.unwrap_or(state.delay).min(MAX_HOST_DELAY);
+            state.next_slot = Some(state.next_slot.map_or(resume, |next_slot| next_slot.max(resume)));
+            return true
+        }
+
+        if let Some(state) = hosts.get_mut(host) {
+            if code == 0 || state.delay == Duration::ZERO { return false }
+            state.normal_responses += 1;
+            if state.normal_responses >= RECOVERY_RESPONSES {
+                state.normal_responses = 0;
+                state.delay /= 2;
+                if state.delay < MIN_HOST_DELAY {
+                    state.delay = Duration::ZERO;
+                    tui::message(format!("Requests to {} are no longer slowed", host));
+                }
+                else {
+                    tui::message(format!("Speeding requests to {} up to one every {}ms", host, state.delay.as_millis()));
+                }
+            }
+        }
+        false
+    }
+}
+
+// Parses a Retry-After value given in seconds, the HTTP date form isn't
+// supported and is treated as if no value was given
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn check_host_throttle() {
+        let throttle = super::HostThrottle::new();
+        let start = Instant::now();
+        assert!(!throttle.add_response_at("example.com", 200, None, start));
+        assert!(throttle.hosts.lock().unwrap().is_empty());
+
+        assert!(throttle.add_response_at("example.com", 429, None, start));
+        // Responses to requests sent before the host was slowed are retried
+        // without slowing it further
+        let soon = start + super::MIN_HOST_DELAY / 2;
+        assert!(throttle.add_response_at("example.com", 503, Some(Duration::from_secs(2)), soon));
+        assert!(!throttle.add_response_at("example.com", 503, None, soon));
+        assert_eq!(throttle.hosts.lock().unwrap()["example.com"].delay, super::MIN_HOST_DELAY);
+        assert_eq!(throttle.hosts.lock().unwrap()["example.com"].next_slot, Some(soon + Duration::from_secs(2)));
+
+        // Once the delay has passed another 429 doubles it
+        let later = start + super::MIN_HOST_DELAY;
+        assert!(throttle.add_response_at("example.com", 429, None, later));
+        assert_eq!(throttle.hosts.lock().unwrap()["example.com"].delay, super::MIN_HOST_DELAY * 2);
+
+        for _ in 0..super::RECOVERY_RESPONSES * 2 {
+            throttle.add_response_at("example.com", 200, None, later);
+        }
+        assert_eq!(throttle.hosts.lock().unwrap()["example.com"].delay, Duration::ZERO);
+        assert!(!throttle.hosts.lock().unwrap().contains_key("example.org"));
+    }
+}
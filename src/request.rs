@@ -16,63 +16,255 @@
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
 use curl::Error;
-use std::sync::Arc;
+use std::cmp::min;
+use std::sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}};
+use std::thread;
 use std::time::Duration;
-use crate::arg_parse::GlobalOpts;
+use crate::arg_parse::{AuthType, GlobalOpts};
 use percent_encoding::percent_decode;
 extern crate curl;
-use curl::easy::{Easy2, Handler, WriteError};
+use curl::easy::{Auth, Easy2, Handler, HttpVersion, WriteError};
+use crate::host_info::{self, CertificateInfo, HostInfo};
 use crate::content_parse;
+use crate::discovered_hosts;
+use crate::hash;
+use crate::output_bodies;
+use crate::output_format;
+use crate::rate_limit::{self, HostThrottle, RateLimiter};
+use crate::rules::{self, RuleAction};
+use crate::tui;
+use crate::url_parse;
+use regex::Regex;
+use std::{ffi::CStr, ptr};
+use rand::{thread_rng, Rng, distributions::Alphanumeric};
+use serde::Serialize;
+
+// Total number of bytes held in Collector buffers across all threads
+static BUFFERED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+// Number of times a request is retried after the host asked for it to slow
+// down, the last response is reported if it's still rejected
+const MAX_THROTTLED_RETRIES: u32 = 3;
 
 pub struct Collector
 {
     pub contents: Vec<u8>,
-    pub content_len: usize
+    pub content_len: usize,
+    pub headers: Vec<String>,
+    // The client profile the easy presents itself as, if enabled
+    pub profile: Option<&'static ClientProfile>,
+    // The generation of the bearer token in the easy's headers
+    pub token_generation: u64,
+    // Shared by every easy when the total request rate is limited
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    // Shared by every easy to slow hosts which are rejecting requests
+    pub host_throttle: Option<Arc<HostThrottle>>,
+    // The user-agents each request picks one from at random, if rotating
+    pub user_agents: Option<Arc<Vec<String>>>,
+    // The Host header sent in place of the url's host, when scanning for
+    // virtual hosts
+    pub host_header: Option<String>,
+    // The Origin header sent when checking a url's CORS policy
+    pub origin_header: Option<String>,
+    // The wordlist item and host the placeholders in the user's headers are
+    // replaced with
+    pub placeholder_values: (String, String),
+    // Maximum number of bytes all collectors can hold at once, 0 for no limit
+    max_buffered: usize
 }
 
 impl Collector {
+    pub fn new(max_buffered: usize) -> Collector {
+        Collector {
+            contents: Vec::new(),
+            content_len: 0,
+            headers: Vec::new(),
+            profile: None,
+            token_generation: 0,
+            rate_limiter: None,
+            host_throttle: None,
+            user_agents: None,
+            host_header: None,
+            origin_header: None,
+            placeholder_values: (String::new(), String::new()),
+            max_buffered
+        }
+    }
+
     fn clear_buffer(&mut self) {
+        BUFFERED_BYTES.fetch_sub(self.contents.len(), Ordering::SeqCst);
         self.contents = Vec::new();
         self.content_len = 0;
+        self.headers.clear();
     }
 }
 
 impl Handler for Collector {
+    // Bodies are truncated once the global cap is reached, but the
+    // full length of the response is still counted
     fn write(&mut self, data: &[u8]) -> Result<usize, WriteError> {
-        self.contents.extend_from_slice(data);
         let data_len = data.len();
         self.content_len += data_len;
+
+        let mut stored = data_len;
+        let mut previous = BUFFERED_BYTES.load(Ordering::SeqCst);
+        loop {
+            if self.max_buffered != 0 {
+                stored = min(data_len, self.max_buffered.saturating_sub(previous));
+            }
+            match BUFFERED_BYTES.compare_exchange(previous, previous + stored,
+                Ordering::SeqCst, Ordering::SeqCst) {
+                Ok(_) => break,
+                Err(current) => previous = current,
+            }
+        }
+        self.contents.extend_from_slice(&data[..stored]);
+
         Ok(data_len)
     }
+
+    // Store each response header line, without the line ending
+    fn header(&mut self, data: &[u8]) -> bool {
+        let header = String::from_utf8_lossy(data).trim().to_string();
+        if !header.is_empty() {
+            self.headers.push(header);
+        }
+        true
+    }
+}
+
+impl Drop for Collector {
+    fn drop(&mut self) {
+        BUFFERED_BYTES.fetch_sub(self.contents.len(), Ordering::SeqCst);
+    }
 }
 
 // Struct which contains information about a response
 // This is sent back to the main thread
+#[derive(Clone, Default, Serialize)]
 pub struct RequestResponse {
     pub url: String,
     pub code: u32,
     pub content_len: usize,
+    // The number of body bytes received, less than content_len when the
+    // response was compressed
+    pub transfer_size: usize,
     pub is_directory: bool,
     pub is_listable: bool,
     pub redirect_url: String,
     pub found_from_listable: bool,
-    pub parent_depth: u32
+    pub parent_depth: u32,
+    pub listable_entries: usize,
+    pub listable_size: Option<u64>,
+    pub cookie_names: Vec<String>,
+    pub security_headers: Vec<String>,
+    pub verb_codes: Vec<(String, u32)>,
+    // Transformed urls which were let through where the url was forbidden,
+    // along with their codes
+    pub bypasses: Vec<(String, u32)>,
+    // The methods listed in the Allow header of an OPTIONS request, only
+    // requested with --allowed-methods
+    pub allowed_methods: Vec<String>,
+    // The crafted origins the url's CORS policy trusted, only checked with
+    // --cors
+    pub cors: Vec<String>,
+    // The url exactly as it was requested, before its encoding was normalised
+    pub raw_url: String,
+    // Keywords which were found in the body of the response
+    pub keywords: Vec<String>,
+    // The HTTP version of the response, e.g. "HTTP/2"
+    pub http_version: String,
+    // The Content-Type and Server headers of the response, empty if not sent
+    pub content_type: String,
+    pub server: String,
+    // The SHA-256 hash of the body, empty if no response was received
+    pub body_sha256: String,
+    // The simhash of the body, for recognising pages which are nearly the same
+    pub body_simhash: u64,
+    // The framework identified from a server error page, e.g. "Django 2.2.4"
+    pub framework: Option<String>,
+    // Paths mentioned in a JSON error body, only searched for in API mode
+    pub endpoint_hints: Vec<String>,
+    // Hostnames under the targets' domains mentioned in the body, only
+    // searched for when collecting discovered hosts
+    pub hostnames: Vec<String>,
+    // Every response header line, only recorded with --capture-headers
+    pub headers: Vec<String>,
+    // The <title> of an HTML response, empty if it has none
+    pub title: String,
+    // The time taken to receive the whole response, in milliseconds
+    pub response_time: u64,
+    // Whether the response time is in the range given with --flag-time
+    pub time_flagged: bool,
+    // Set for directories which matched a no-recurse rule
+    pub skip_recursion: bool
+}
+
+// Response headers which are recorded when present
+pub const SECURITY_HEADERS: [&str; 9] = [
+    "strict-transport-security",
+    "content-security-policy",
+    "x-frame-options",
+    "x-content-type-options",
+    "x-xss-protection",
+    "referrer-policy",
+    "permissions-policy",
+    "access-control-allow-origin",
+    "www-authenticate"
+];
+
+// The headers a browser sends, used to make each thread look like a
+// separate client when client profiles are enabled
+pub struct ClientProfile {
+    pub user_agent: &'static str,
+    pub accept: &'static str,
+    pub accept_language: &'static str
 }
 
+const CLIENT_PROFILES: [ClientProfile; 6] = [
+    ClientProfile {
+        user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36",
+        accept: "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8",
+        accept_language: "en-US,en;q=0.9"
+    },
+    ClientProfile {
+        user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:125.0) Gecko/20100101 Firefox/125.0",
+        accept: "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+        accept_language: "en-GB,en;q=0.5"
+    },
+    ClientProfile {
+        user_agent: "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.4 Safari/605.1.15",
+        accept: "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+        accept_language: "en-US,en;q=0.9"
+    },
+    ClientProfile {
+        user_agent: "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Mobile Safari/537.36",
+        accept: "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8",
+        accept_language: "de-DE,de;q=0.9,en;q=0.8"
+    },
+    ClientProfile {
+        user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/124.0.0.0 Safari/537.36 Edg/124.0.0.0",
+        accept: "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,*/*;q=0.8",
+        accept_language: "fr-FR,fr;q=0.9,en;q=0.8"
+    },
+    ClientProfile {
+        user_agent: "Mozilla/5.0 (X11; Linux x86_64; rv:125.0) Gecko/20100101 Firefox/125.0",
+        accept: "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+        accept_language: "en-US,en;q=0.5"
+    }
+];
+
 // This function takes an instance of "Easy2", a base URL and a suffix
 // It then makes the request, if the response was not a 404
 // then it will return a RequestResponse struct
-pub fn make_request(mut easy: &mut Easy2<Collector>, url: String) -> RequestResponse{
-
-    // Set the url in the Easy2 instance
-    easy.url(&url).unwrap();
+pub fn make_request(easy: &mut Easy2<Collector>, url: String) -> RequestResponse{
 
     // Perform the request and check if it's empty
     // If it's empty then return a RequestResponse struct
-    match perform(&mut easy) {
+    match perform(easy, &url) {
         Ok(_v) => {}
         Err(e) => {
-            println!("Curl error after requesting {} : {}", url, e);
+            tui::message(format!("Curl error after requesting {} : {}", url, e));
             let req_response = RequestResponse {
                 url: url.clone(),
                 code: 0,
@@ -81,7 +273,8 @@ pub fn make_request(mut easy: &mut Easy2<Collector>, url: String) -> RequestResp
                 is_listable: false,
                 redirect_url: String::from(""),
                 found_from_listable: false,
-                parent_depth: 0
+                parent_depth: 0,
+                ..Default::default()
             };
             return req_response; 
         }
@@ -99,44 +292,85 @@ pub fn make_request(mut easy: &mut Easy2<Collector>, url: String) -> RequestResp
         is_listable: false,
         redirect_url: String::from(""),
         found_from_listable: false,
-        parent_depth: 0
+        parent_depth: 0,
+        ..Default::default()
     };
 
+    // Record the names of cookies and security headers the response set
+    let headers = &easy.get_ref().headers;
+    req_response.http_version = http_version(headers);
+    req_response.content_type = header_values(headers, "content-type").pop().unwrap_or_default();
+    req_response.server = header_values(headers, "server").pop().unwrap_or_default();
+    req_response.body_sha256 = hash::sha256(&easy.get_ref().contents);
+    req_response.body_simhash = content_parse::simhash(&easy.get_ref().contents);
+    let media_type = output_format::media_type(&req_response.content_type).to_lowercase();
+    if media_type == "text/html" || media_type == "application/xhtml+xml" {
+        req_response.title = content_parse::page_title(&String::from_utf8_lossy(&easy.get_ref().contents))
+            .unwrap_or_default();
+    }
+    req_response.cookie_names = header_values(headers, "set-cookie").iter()
+        .filter_map(|cookie| cookie.split('=').next())
+        .map(|name| name.trim().to_string())
+        .collect();
+    req_response.security_headers = SECURITY_HEADERS.iter()
+        .filter(|name| !header_values(headers, name).is_empty())
+        .map(|name| name.to_string())
+        .collect();
+
     // If the response was a redirect, check if it's a directory
-    // Also add the redirect url to the struct
+    // Also add the redirect destination to the struct
     // Generally, directories will redirect requests to them with no trailing /
     // so that they have a trailing /
-    if code == 301 || code == 302 {
+    if (300..400).contains(&code) {
+        if let Ok(Some(redir_dest)) = easy.redirect_url() {
+            // Url decode the redirect destination
+            let redir_dest = percent_decode(redir_dest.as_bytes()).decode_utf8_lossy().to_string();
 
-        // Obtain and url decode the redirect destination
-        let redir_dest = easy.redirect_url().unwrap().unwrap();
-        let redir_dest = percent_decode(redir_dest.as_bytes()).decode_utf8().unwrap();
+            // Clone and url decode the url
+            let dir_url = url.clone() + "/";
+            let dir_url = percent_decode(dir_url.as_bytes()).decode_utf8().unwrap();
 
-        // Clone and url decode the url
-        let dir_url = url.clone() + "/";
-        let dir_url = percent_decode(dir_url.as_bytes()).decode_utf8().unwrap();
+            if dir_url == redir_dest {
+                req_response.is_directory = true;
+            }
 
-        if dir_url == redir_dest {
-            req_response.is_directory = true;
+            req_response.redirect_url = redir_dest;
         }
-
-        req_response.redirect_url = dir_url.to_string();
     }
 
     // Get the contents of the response and set the length in the struct
+    // The length is of the decoded body, which may have been compressed
     let contents = easy.get_ref();
     req_response.content_len = contents.content_len;
+    req_response.transfer_size = easy.download_size().unwrap_or(0.0) as usize;
+    req_response.response_time = easy.total_time().map_or(0, |time| time.as_millis() as u64);
 
     req_response
 }
 
-pub fn listable_check(easy: &mut Easy2<Collector>, original_url: String, disable_recursion: bool, scrape_listable: bool) -> Vec<RequestResponse> {
+pub fn listable_check(easy: &mut Easy2<Collector>, original_url: String, global_opts: &GlobalOpts) -> Vec<RequestResponse> {
     // Formulate the directory name and make a request to get the contents of the page
     let mut dir_url = String::from(original_url.clone());
     if !dir_url.ends_with("/") {
         dir_url = dir_url + "/";
     }
     let mut response = make_request(easy, dir_url.clone());
+
+    // Rules are applied to the listing too, as it says more about the
+    // directory than the redirect to it
+    let rule_action = rules::matching_action(&global_opts.rules, easy, &response);
+    if rule_action == Some(RuleAction::NotFound) { return Vec::new() }
+    let skip_recursion = rule_action == Some(RuleAction::NoRecurse);
+    response.skip_recursion = skip_recursion;
+
+    if global_opts.capture_headers && response.code != 0 {
+        response.headers = captured_headers(easy);
+    }
+    if let Some(directory) = &global_opts.save_bodies {
+        if response.code == 200 {
+            output_bodies::save(directory, &response, &easy.get_ref().contents);
+        }
+    }
     let content = get_content(easy).to_lowercase();
     let mut output_list:Vec<RequestResponse> = Vec::new();
 
@@ -149,6 +383,9 @@ pub fn listable_check(easy: &mut Easy2<Collector>, original_url: String, disable
             if listable{
                 response.is_listable = true;
                 response.is_directory = true;
+                response.listable_entries = content_parse::scrape_urls(
+                    content.clone(), dir_url.clone()).len();
+                response.listable_size = content_parse::listing_size(&content);
                 output_list.push(response);
             }
             else{
@@ -162,31 +399,48 @@ pub fn listable_check(easy: &mut Easy2<Collector>, original_url: String, disable
         // If the code returned was not a 200 then create a struct
         // indicating that this is a folder, then return it
         _ => {
-            output_list.push(fabricate_request_response(
-                original_url, true, false));
+            let mut fabricated = fabricate_request_response(original_url, true, false);
+            fabricated.skip_recursion = skip_recursion;
+            output_list.push(fabricated);
             return output_list
         }
     }
 
     // If scraping of listables is disabled then just return from the function
-    if !scrape_listable { return output_list }
+    if !global_opts.scrape_listable { return output_list }
 
     // Get urls scraped from the response
     let scraped_urls:Vec<String> = content_parse::scrape_urls(content, dir_url);
 
     for scraped_url in scraped_urls {
+        if let Some(scope) = &global_opts.scope {
+            if !scope.contains(&scraped_url) { continue }
+        }
+
         // If the scraped url doesn't end in a /, it's unlikely to be a folder
         // Add it to the list of found URLs to be returned
         if !scraped_url.ends_with("/") {
-            output_list.push(fabricate_request_response(
-                scraped_url, false, false));
+            // Request the url to get its real code and size if verification is enabled
+            if global_opts.verify_scraped {
+                let mut verified_response = make_request(easy, scraped_url);
+                verified_response.found_from_listable = true;
+                output_list.push(verified_response);
+
+                if global_opts.throttle != 0 {
+                    thread::sleep(Duration::from_millis(global_opts.throttle as u64));
+                }
+            }
+            else {
+                output_list.push(fabricate_request_response(
+                    scraped_url, false, false));
+            }
         }
         // If the url ends in a /, it is likely to be a folder
         else {
             // If recursion is enabled then call this function on the discovered folder
             // Append the discovered items to the current output
-            if !disable_recursion {
-                output_list.append(&mut listable_check(easy, scraped_url, disable_recursion, scrape_listable));
+            if !global_opts.disable_recursion {
+                output_list.append(&mut listable_check(easy, scraped_url, global_opts));
             }
             // If recursion is disabled then just add the url to the values to be returned
             else {
@@ -201,33 +455,107 @@ pub fn listable_check(easy: &mut Easy2<Collector>, original_url: String, disable
 // Creates an easy2 instance based on the parameters provided by the user
 pub fn generate_easy(global_opts: Arc<GlobalOpts>) -> Easy2<Collector>
 {
-    // Create a new curl Easy2 instance and set it to use GET requests
-    let mut easy = Easy2::new(Collector{contents: Vec::new(), content_len: 0});
-    easy.get(true).unwrap();
+    // Create a new curl Easy2 instance and set it to use the scan's verb
+    let mut easy = Easy2::new(Collector::new(global_opts.max_buffer_memory * 1024 * 1024));
+    easy.get_mut().rate_limiter = global_opts.rate_limiter.clone();
+    easy.get_mut().host_throttle = global_opts.host_throttle.clone();
+    easy.get_mut().user_agents = global_opts.user_agents.clone();
+    set_scan_verb(&mut easy, &global_opts);
 
     // Set the timeout of the easy
     easy.timeout(Duration::from_secs(global_opts.timeout as u64)).unwrap();
+    if let Some(connect_timeout) = global_opts.connect_timeout {
+        easy.connect_timeout(Duration::from_secs(connect_timeout as u64)).unwrap();
+    }
+
+    // Keep idle connections open so they can be reused by later requests,
+    // including by other threads once the easy is returned to the pool
+    easy.tcp_keepalive(true).unwrap();
+
+    // Ask for compressed responses using every encoding curl can decode,
+    // bodies are decoded before they're collected
+    easy.accept_encoding("").unwrap();
+
+    // Negotiate HTTP/2 over TLS where the server supports it, optionally
+    // also asking plain http connections to upgrade
+    // HTTP/3 falls back to the earlier versions if the QUIC connection fails
+    if global_opts.http3 {
+        easy.http_version(HttpVersion::V3).unwrap();
+    }
+    else if global_opts.http2 {
+        easy.http_version(HttpVersion::V2).unwrap();
+    }
+    else {
+        // libcurl builds without HTTP/2 support reject this and keep using HTTP/1.1
+        let _ = easy.http_version(HttpVersion::V2TLS);
+    }
 
     // Use proxy settings if they have been provided
     if global_opts.proxy_enabled {
         easy.proxy(&global_opts.proxy_address).unwrap();
     }
 
+    // Send requests out of the given interface or address
+    if let Some(interface) = &global_opts.interface {
+        easy.interface(interface).unwrap();
+    }
+
+    // Connect to the given addresses instead of resolving the hosts
+    if !global_opts.resolve.is_empty() {
+        let mut resolve_list = curl::easy::List::new();
+        for resolve in &global_opts.resolve {
+            resolve_list.append(resolve).unwrap();
+        }
+        easy.resolve(resolve_list).unwrap();
+    }
+
     // If the ignore cert flag is enabled, ignore cert validity
     if global_opts.ignore_cert {
         easy.ssl_verify_host(false).unwrap();
         easy.ssl_verify_peer(false).unwrap();
     }
 
+    // Present a client certificate to targets requiring mutual TLS
+    if let Some(client_cert) = &global_opts.client_cert {
+        easy.ssl_cert(client_cert).unwrap();
+        easy.ssl_cert_type(client_cert_type(client_cert)).unwrap();
+        if let Some(client_key) = &global_opts.client_key {
+            easy.ssl_key(client_key).unwrap();
+            easy.ssl_key_type("PEM").unwrap();
+        }
+        if let Some(password) = &global_opts.client_key_password {
+            easy.key_password(password).unwrap();
+        }
+    }
+
+    // Present as a randomly chosen client, with its own cookie jar so that
+    // cookies set by the server are sent back like a browser would
+    if global_opts.client_profiles {
+        let profile = &CLIENT_PROFILES[thread_rng().gen_range(0..CLIENT_PROFILES.len())];
+        easy.get_mut().profile = Some(profile);
+        easy.useragent(profile.user_agent).unwrap();
+        easy.cookie_file("").unwrap();
+    }
+
     // Set the user agent
     if let Some(user_agent) = &global_opts.user_agent {
         easy.useragent(&user_agent.clone()).unwrap();
     }
 
-    // Set http basic auth options
-    if let Some(username) = &global_opts.username {
-        easy.username(&username.clone()).unwrap();
-        easy.password(&global_opts.password.clone().unwrap()).unwrap();
+    // Set http auth options, negotiate can run without credentials by
+    // using the current Kerberos ticket
+    if global_opts.username.is_some() || global_opts.auth_type == AuthType::Negotiate {
+        easy.username(global_opts.username.as_deref().unwrap_or("")).unwrap();
+        easy.password(global_opts.password.as_deref().unwrap_or("")).unwrap();
+
+        easy.http_auth(&curl_auth(global_opts.auth_type)).unwrap();
+    }
+
+    // Set proxy auth options
+    if global_opts.proxy_auth_enabled {
+        easy.proxy_username(global_opts.proxy_username.as_deref().unwrap_or("")).unwrap();
+        easy.proxy_password(global_opts.proxy_password.as_deref().unwrap_or("")).unwrap();
+        easy.proxy_auth(&curl_auth(global_opts.proxy_auth_type)).unwrap();
     }
 
     // Set cookies
@@ -236,23 +564,607 @@ pub fn generate_easy(global_opts: Arc<GlobalOpts>) -> Easy2<Collector>
     }
 
     // Set headers
-    if let Some(headers) =  &global_opts.headers {
-        let mut header_list = curl::easy::List::new();
-        for header in headers {
-            header_list.append(header).unwrap();
-        }
-        easy.http_headers(header_list).unwrap();
+    if global_opts.headers.is_some() || global_opts.spoof_ip.is_some() || global_opts.client_profiles
+        || global_opts.content_type.is_some() || global_opts.bearer_token.is_some() {
+        set_headers(&mut easy, &global_opts);
     }
 
     easy
 }
 
+// Easies left by finished scan threads, each with the base url it last
+// requested, so that the next thread can reuse the connections they hold
+// open rather than paying for a new TCP and TLS handshake on every directory
+#[derive(Default)]
+pub struct EasyPool {
+    easies: Mutex<Vec<(String, Easy2<Collector>)>>
+}
+
+impl EasyPool {
+    pub fn new() -> EasyPool {
+        EasyPool::default()
+    }
+
+    // Takes an easy to scan the url with, preferring one which last requested
+    // the same host, and creates a new one if none are left
+    pub fn take(&self, url: &str, global_opts: Arc<GlobalOpts>) -> Easy2<Collector> {
+        let base_url = url_parse::base_url(url);
+        let easy = {
+            let mut easies = self.easies.lock().unwrap();
+            easies.iter().position(|(easy_url, _)| *easy_url == base_url)
+                .or_else(|| easies.len().checked_sub(1))
+                .map(|index| easies.swap_remove(index).1)
+        };
+
+        match easy {
+            Some(mut easy) => {
+                // Undo anything the previous thread changed for its own scan
+                easy.get_mut().host_header = None;
+                easy.get_mut().placeholder_values = (String::new(), String::new());
+                set_scan_verb(&mut easy, &global_opts);
+                set_headers(&mut easy, &global_opts);
+                easy
+            },
+            None => generate_easy(global_opts)
+        }
+    }
+
+    // Returns an easy to the pool once a thread has finished scanning the url
+    pub fn put(&self, url: &str, mut easy: Easy2<Collector>) {
+        easy.get_mut().clear_buffer();
+        self.easies.lock().unwrap().push((url_parse::base_url(url), easy));
+    }
+}
+
+// Sets the user's headers on the easy, along with the headers claiming the
+// spoofed address as the client's address if enabled
+// A random address is chosen each time when spoofing random addresses
+pub fn set_headers(easy: &mut Easy2<Collector>, global_opts: &GlobalOpts)
+{
+    let mut header_list = curl::easy::List::new();
+    let user_headers = global_opts.headers.clone().unwrap_or_default();
+    let (word, host) = &easy.get_ref().placeholder_values;
+    for header in &user_headers {
+        header_list.append(&expand_placeholders(header, word, host)).unwrap();
+    }
+
+    if let Some(content_type) = &global_opts.content_type {
+        header_list.append(&format!("Content-Type: {}", content_type)).unwrap();
+    }
+
+    if let Some(host) = &easy.get_ref().host_header {
+        header_list.append(&format!("Host: {}", host)).unwrap();
+    }
+
+    if let Some(origin) = &easy.get_ref().origin_header {
+        header_list.append(&format!("Origin: {}", origin)).unwrap();
+    }
+
+    // Headers given by the user take precedence over the client profile's
+    if let Some(profile) = easy.get_ref().profile {
+        for (name, value) in [("Accept", profile.accept), ("Accept-Language", profile.accept_language)] {
+            if header_values(&user_headers, name).is_empty() {
+                header_list.append(&format!("{}: {}", name, value)).unwrap();
+            }
+        }
+    }
+
+    if let Some(bearer_token) = &global_opts.bearer_token {
+        let (token, generation) = bearer_token.current();
+        header_list.append(&format!("Authorization: Bearer {}", token)).unwrap();
+        easy.get_mut().token_generation = generation;
+    }
+
+    if let Some(spoof_ip) = &global_opts.spoof_ip {
+        let address = if spoof_ip == "random" { random_ip() } else { spoof_ip.clone() };
+        for header in &global_opts.spoof_headers {
+            let header = match header.as_str() {
+                "x-forwarded-for" => format!("X-Forwarded-For: {}", address),
+                "x-real-ip" => format!("X-Real-IP: {}", address),
+                // IPv6 addresses have to be quoted and bracketed in Forwarded
+                _ if address.contains(':') => format!("Forwarded: for=\"[{}]\"", address),
+                _ => format!("Forwarded: for={}", address)
+            };
+            header_list.append(&header).unwrap();
+        }
+    }
+
+    easy.http_headers(header_list).unwrap();
+}
+
+// Placeholders which can be used in the user's headers
+const PLACEHOLDERS: [&str; 3] = ["{rand}", "{word}", "{host}"];
+
+// Checks whether any of the user's headers contain placeholders, in which
+// case the headers have to be set again for each request
+pub fn has_placeholders(global_opts: &GlobalOpts) -> bool
+{
+    global_opts.headers.iter().flatten()
+        .any(|header| PLACEHOLDERS.iter().any(|placeholder| header.contains(placeholder)))
+}
+
+// Sets the user's headers with their placeholders replaced for a request
+pub fn set_placeholder_headers(easy: &mut Easy2<Collector>, global_opts: &GlobalOpts, word: &str, url: &str)
+{
+    easy.get_mut().placeholder_values = (String::from(word), url_parse::host(url));
+    set_headers(easy, global_opts);
+}
+
+// Replaces each {rand} with a different random string, and {word} and
+// {host} with the given values
+fn expand_placeholders(header: &str, word: &str, host: &str) -> String
+{
+    if !header.contains('{') { return String::from(header) }
+
+    let mut parts = header.split("{rand}");
+    let mut expanded = String::from(parts.next().unwrap_or(""));
+    for part in parts {
+        expanded += &thread_rng().sample_iter(&Alphanumeric).take(16).map(char::from).collect::<String>();
+        expanded += part;
+    }
+    expanded.replace("{word}", word).replace("{host}", host)
+}
+
+// Picks up a new bearer token after a request was rejected with a 401,
+// returning true if the request should be retried, either with the new
+// token or because the easy was used to check the old one
+// The token is only refreshed after a run of 401s, once a url which
+// accepted it before rejects it too
+pub fn refresh_bearer_token(easy: &mut Easy2<Collector>, global_opts: &GlobalOpts) -> bool
+{
+    let bearer_token = match &global_opts.bearer_token {
+        Some(bearer_token) => bearer_token,
+        None => return false
+    };
+    let generation = easy.get_ref().token_generation;
+    if generation != bearer_token.current().1 {
+        set_headers(easy, global_opts);
+        return true
+    }
+
+    let accepted_url = match bearer_token.rejected(generation) {
+        Some(url) => url,
+        None => return false
+    };
+    if make_request(easy, accepted_url).code != 401 {
+        bearer_token.still_accepted();
+    }
+    else if bearer_token.refresh(generation) {
+        set_headers(easy, global_opts);
+    }
+    true
+}
+
+// Generates a random unicast IPv4 address
+fn random_ip() -> String
+{
+    let mut rng = thread_rng();
+    format!("{}.{}.{}.{}", rng.gen_range(1..224), rng.gen::<u8>(), rng.gen::<u8>(), rng.gen_range(1..255))
+}
+
+fn curl_auth(auth_type: AuthType) -> Auth
+{
+    let mut auth = Auth::new();
+    match auth_type {
+        AuthType::Basic => auth.basic(true),
+        AuthType::Digest => auth.digest(true),
+        AuthType::Ntlm => auth.ntlm(true),
+        AuthType::Negotiate => auth.gssnegotiate(true)
+    };
+    auth
+}
+
+// Returns the curl certificate type for the client certificate file
+fn client_cert_type(filename: &str) -> &'static str
+{
+    let lowercase = filename.to_lowercase();
+    if lowercase.ends_with(".p12") || lowercase.ends_with(".pfx") { "P12" } else { "PEM" }
+}
+
+// Sets the verb the scan uses, along with the request body if one was given
+pub fn set_scan_verb(easy: &mut Easy2<Collector>, global_opts: &GlobalOpts)
+{
+    set_verb(easy, global_opts.http_verb.as_str());
+    if let Some(body) = &global_opts.request_body {
+        easy.post_fields_copy(body).unwrap();
+    }
+}
+
+// Sets the HTTP verb used by future requests made with the easy
+pub fn set_verb(easy: &mut Easy2<Collector>, verb: &str)
+{
+    easy.get(true).unwrap();
+    easy.nobody(verb == "HEAD").unwrap();
+    easy.custom_request(verb).unwrap();
+}
+
+// Requests a random path in the given directory with each of the comparison
+// verbs, giving the codes returned for a path which doesn't exist
+pub fn verb_baseline(easy: &mut Easy2<Collector>, base_url: &str, verbs: &[String],
+    global_opts: &GlobalOpts) -> Vec<(String, u32)>
+{
+    let random_path: String = thread_rng().sample_iter(&Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect();
+    let url = format!("{}/{}", base_url, random_path);
+
+    let baseline = verbs.iter()
+        .map(|verb| (verb.clone(), verb_code(easy, &url, verb)))
+        .collect();
+    set_scan_verb(easy, global_opts);
+
+    baseline
+}
+
+// Requests the url with each of the comparison verbs, returning the
+// verbs which gave a different code to both the GET request and
+// the code given for that verb by a path which doesn't exist
+pub fn compare_verbs(easy: &mut Easy2<Collector>, url: &str, scan_code: u32,
+    baseline: &[(String, u32)], global_opts: &GlobalOpts) -> Vec<(String, u32)>
+{
+    let mut differing = Vec::new();
+    for (verb, baseline_code) in baseline {
+        let code = verb_code(easy, url, verb);
+        if code != scan_code && code != *baseline_code {
+            differing.push((verb.clone(), code));
+        }
+    }
+    set_scan_verb(easy, global_opts);
+
+    differing
+}
+
+// Requests the url with OPTIONS, returning the methods listed in the Allow
+// header of the response
+pub fn allowed_methods(easy: &mut Easy2<Collector>, url: &str, global_opts: &GlobalOpts) -> Vec<String>
+{
+    set_verb(easy, "OPTIONS");
+    let methods = match perform(easy, url) {
+        Ok(_) => header_values(&easy.get_ref().headers, "allow").iter()
+            .flat_map(|allow| allow.split(','))
+            .map(|method| method.trim().to_uppercase())
+            .filter(|method| !method.is_empty())
+            .collect(),
+        Err(_) => Vec::new()
+    };
+    set_scan_verb(easy, global_opts);
+
+    methods
+}
+
+// Requests the url with each of the crafted origins, returning those which
+// the response's CORS headers trust, noting when credentials are allowed too
+pub fn cors_misconfigurations(easy: &mut Easy2<Collector>, url: &str, global_opts: &GlobalOpts) -> Vec<String>
+{
+    let mut findings = Vec::new();
+    for origin in cors_origins(url) {
+        easy.get_mut().origin_header = Some(origin.clone());
+        set_headers(easy, global_opts);
+        if perform(easy, url).is_err() { continue }
+
+        let headers = &easy.get_ref().headers;
+        let allowed_origin = header_values(headers, "access-control-allow-origin").pop().unwrap_or_default();
+        let credentials = header_values(headers, "access-control-allow-credentials").pop()
+            .is_some_and(|credentials| credentials.eq_ignore_ascii_case("true"));
+        if let Some(finding) = cors_finding(&origin, &allowed_origin, credentials) {
+            if !findings.contains(&finding) {
+                findings.push(finding);
+            }
+        }
+    }
+    easy.get_mut().origin_header = None;
+    set_headers(easy, global_opts);
+
+    findings
+}
+
+// The origins a url's CORS policy is checked with: an unrelated site, one
+// which passes checks that only look for the target's host in the origin,
+// and the null origin given to sandboxed pages and local files
+fn cors_origins(url: &str) -> Vec<String>
+{
+    vec![
+        String::from("https://dirble.example"),
+        format!("https://{}.dirble.example", url_parse::host(url)),
+        String::from("null")
+    ]
+}
+
+// Describes the trust the CORS headers give the crafted origin, a wildcard
+// is only reported when it comes with credentials as it is otherwise
+// normal for public resources
+fn cors_finding(origin: &str, allowed_origin: &str, credentials: bool) -> Option<String>
+{
+    let finding = if allowed_origin == origin { origin }
+        else if allowed_origin == "*" && credentials { "*" }
+        else { return None };
+
+    if credentials { Some(format!("{} with credentials", finding)) } else { Some(String::from(finding)) }
+}
+
+// Requests the forbidden url with each of the bypass transforms, returning
+// the urls which were let through along with their codes
+// Only success codes are kept, as the transformed urls often just give a 400
+// or 404, or redirect to a login page or the canonical url
+pub fn check_bypasses(easy: &mut Easy2<Collector>, url: &str) -> Vec<(String, u32)>
+{
+    // Curl would otherwise remove the encoded dot segments before sending
+    easy.path_as_is(true).unwrap();
+    let bypasses = bypass_urls(url).into_iter()
+        .filter_map(|bypass_url| match perform(easy, &bypass_url) {
+            Ok(_) => Some((bypass_url, easy.response_code().unwrap())),
+            Err(_) => None
+        })
+        .filter(|(_, code)| (200..300).contains(code))
+        .collect();
+    easy.path_as_is(false).unwrap();
+
+    bypasses
+}
+
+// Generates variations of the url's last segment which servers, or the
+// proxies in front of them, often treat differently to the access rules
+// for the path, e.g. /admin becomes /%2e/admin, //admin, /admin;/, /ADMIN
+// and /admin%20
+fn bypass_urls(url: &str) -> Vec<String>
+{
+    let trimmed = url.trim_end_matches('/');
+    let slash = if trimmed.len() == url.len() { "" } else { "/" };
+    let (parent, name) = match trimmed.rfind('/') {
+        Some(index) => trimmed.split_at(index + 1),
+        None => return Vec::new()
+    };
+    // The root of a host has no segment to transform
+    if name.is_empty() || parent.ends_with("//") { return Vec::new() }
+
+    let mut urls = vec![
+        format!("{}%2e/{}{}", parent, name, slash),
+        format!("{}/{}{}", parent, name, slash),
+        format!("{}{};/", parent, name)
+    ];
+    let flipped: String = name.chars()
+        .map(|c| if c.is_lowercase() { c.to_ascii_uppercase() } else { c.to_ascii_lowercase() })
+        .collect();
+    if flipped != name {
+        urls.push(format!("{}{}{}", parent, flipped, slash));
+    }
+    urls.push(format!("{}{}%20{}", parent, name, slash));
+    urls
+}
+
+// Identifies the framework which generated the body of the last response
+pub fn error_framework(easy: &Easy2<Collector>) -> Option<String>
+{
+    content_parse::error_framework(&String::from_utf8_lossy(&easy.get_ref().contents))
+}
+
+// Returns the paths mentioned in the body of the last response if it is JSON
+pub fn endpoint_hints(easy: &Easy2<Collector>) -> Vec<String>
+{
+    content_parse::json_endpoint_hints(&String::from_utf8_lossy(&easy.get_ref().contents))
+}
+
+// Returns the hostnames matching the pattern in the body of the last response
+pub fn body_hostnames(easy: &Easy2<Collector>, pattern: &Regex) -> Vec<String>
+{
+    discovered_hosts::hostnames_in(&String::from_utf8_lossy(&easy.get_ref().contents), pattern)
+}
+
+// Returns the certificate chain of the last connection, with each certificate
+// given as a list of "Name:value" fields
+// The easy must have had certinfo enabled before the request was made
+pub fn certificate_chain(easy: &Easy2<Collector>) -> Vec<Vec<String>>
+{
+    let mut certinfo: *mut curl_sys::curl_certinfo = ptr::null_mut();
+    // Safety: CURLINFO_CERTINFO gives a pointer to a curl_certinfo owned by
+    // the easy, which remains valid until the next request is made with it
+    unsafe {
+        let result = curl_sys::curl_easy_getinfo(easy.raw(), curl_sys::CURLINFO_CERTINFO, &mut certinfo);
+        if result != curl_sys::CURLE_OK || certinfo.is_null() { return Vec::new() }
+
+        let certinfo = &*certinfo;
+        (0..certinfo.num_of_certs.max(0) as usize)
+            .map(|index| {
+                let mut fields = Vec::new();
+                let mut item = *certinfo.certinfo.add(index);
+                while !item.is_null() {
+                    fields.push(CStr::from_ptr((*item).data).to_string_lossy().to_string());
+                    item = (*item).next;
+                }
+                fields
+            })
+            .collect()
+    }
+}
+
+// Returns the details of the certificate the last connection was made with,
+// or None if it wasn't made over TLS or certinfo wasn't enabled
+pub fn certificate_info(easy: &Easy2<Collector>) -> Option<CertificateInfo>
+{
+    certificate_chain(easy).first().map(|fields| CertificateInfo::from_fields(fields))
+}
+
+// Requests the root of the host and returns the details of the response and
+// the certificate, or None if no response was received
+pub fn host_info(easy: &mut Easy2<Collector>, base_url: &str) -> Option<HostInfo>
+{
+    easy.certinfo(base_url.starts_with("https://")).unwrap();
+    let response = make_request(easy, format!("{}/", base_url));
+    let info = last_host_info(easy, base_url);
+    easy.certinfo(false).unwrap();
+    if response.code == 0 { None } else { Some(info) }
+}
+
+// Returns the details of the last response, which should be to the root of
+// the host, so that requests already made to it don't have to be repeated
+// The certificate is only included if certinfo was enabled for the request
+pub fn last_host_info(easy: &Easy2<Collector>, base_url: &str) -> HostInfo
+{
+    let https = base_url.starts_with("https://");
+    let headers = &easy.get_ref().headers;
+    let redirect = easy.redirect_url().ok().flatten()
+        .map(|redirect| percent_decode(redirect.as_bytes()).decode_utf8_lossy().to_string())
+        .filter(|redirect| url_parse::base_url(redirect) != base_url);
+    let security_headers = host_info::AUDITED_HEADERS.iter()
+        .filter(|name| https || **name != "Strict-Transport-Security")
+        .map(|name| (name.to_string(), header_values(headers, name).pop()))
+        .collect();
+
+    HostInfo {
+        http_version: http_version(headers),
+        server: header_values(headers, "server").pop().unwrap_or_default(),
+        redirect,
+        certificate: if https { certificate_info(easy) } else { None },
+        security_headers
+    }
+}
+
+// Requests an evenly spread sample of the findings with HEAD, recording a
+// HEAD verb code on any which respond differently to the GET request
+// Returns the number of findings checked and the urls which were inconsistent
+pub fn head_check(responses: &mut [RequestResponse], global_opts: Arc<GlobalOpts>,
+    sample_size: usize) -> (usize, Vec<(String, u32, u32)>)
+{
+    let candidates: Vec<usize> = responses.iter().enumerate()
+        .filter(|(_, response)| response.code != 0)
+        .map(|(index, _)| index)
+        .collect();
+    if candidates.is_empty() { return (0, Vec::new()) }
+
+    let step = candidates.len().checked_div(sample_size).unwrap_or(1).max(1);
+    let sample: Vec<usize> = candidates.into_iter().step_by(step)
+        .take(if sample_size == 0 { usize::MAX } else { sample_size })
+        .collect();
+
+    let mut easy = generate_easy(global_opts.clone());
+    let mut inconsistent = Vec::new();
+    for index in &sample {
+        let response = &mut responses[*index];
+        let head_code = verb_code(&mut easy, &response.url, "HEAD");
+        if head_code != response.code {
+            inconsistent.push((response.url.clone(), response.code, head_code));
+            if !response.verb_codes.iter().any(|(verb, _)| verb == "HEAD") {
+                response.verb_codes.push((String::from("HEAD"), head_code));
+            }
+        }
+
+        if global_opts.throttle != 0 {
+            thread::sleep(Duration::from_millis(global_opts.throttle as u64));
+        }
+    }
+
+    (sample.len(), inconsistent)
+}
+
+// Returns each of the keywords which appear in the body of the last
+// response, ignoring case
+pub fn match_keywords(easy: &Easy2<Collector>, keywords: &[String]) -> Vec<String>
+{
+    let body = String::from_utf8_lossy(&easy.get_ref().contents).to_lowercase();
+    keywords.iter()
+        .filter(|keyword| body.contains(&keyword.to_lowercase()))
+        .cloned()
+        .collect()
+}
+
+// Checks the body of the last response against --match-regex and
+// --filter-regex, and each of its header lines against --match-header and
+// --filter-header, returning true if the response should be left out
+pub fn response_filtered(easy: &Easy2<Collector>, global_opts: &GlobalOpts) -> bool
+{
+    let headers = &easy.get_ref().headers;
+    let header_matches = |regex: &Regex| headers.iter().any(|header| regex.is_match(header));
+    if global_opts.match_header.as_ref().is_some_and(|regex| !header_matches(regex))
+        || global_opts.filter_header.as_ref().is_some_and(header_matches) {
+        return true
+    }
+
+    if global_opts.match_regex.is_none() && global_opts.filter_regex.is_none() { return false }
+
+    let body = String::from_utf8_lossy(&easy.get_ref().contents);
+    global_opts.match_regex.as_ref().is_some_and(|regex| !regex.is_match(&body))
+        || global_opts.filter_regex.as_ref().is_some_and(|regex| regex.is_match(&body))
+}
+
+// Makes a request with the given verb and returns the response code, or 0 on error
+fn verb_code(easy: &mut Easy2<Collector>, url: &str, verb: &str) -> u32
+{
+    set_verb(easy, verb);
+    match perform(easy, url) {
+        Ok(_) => easy.response_code().unwrap(),
+        Err(_) => 0
+    }
+}
+
+// Requests the url, waiting for the rate limits first and choosing a new
+// user-agent if they are being rotated
 // Before each request, the buffer should be cleared
 // This provides support for chunked http responses
-fn perform(easy: &mut Easy2<Collector>) -> Result<(), Error>
+pub fn perform(easy: &mut Easy2<Collector>, url: &str) -> Result<(), Error>
+{
+    easy.url(url).unwrap();
+    let host = url_parse::host(url);
+    let mut retries = 0;
+    loop {
+        if let Some(rate_limiter) = &easy.get_ref().rate_limiter {
+            rate_limiter.wait();
+        }
+        if let Some(host_throttle) = &easy.get_ref().host_throttle {
+            host_throttle.wait(&host);
+        }
+        if let Some(user_agents) = easy.get_ref().user_agents.clone() {
+            easy.useragent(&user_agents[thread_rng().gen_range(0..user_agents.len())]).unwrap();
+        }
+        easy.get_mut().clear_buffer();
+        easy.perform()?;
+
+        // Requests the host pushed back on are retried once it's been slowed
+        let host_throttle = match &easy.get_ref().host_throttle {
+            Some(host_throttle) => host_throttle.clone(),
+            None => return Ok(())
+        };
+        let code = easy.response_code().unwrap_or(0);
+        let retry_after = header_values(&easy.get_ref().headers, "retry-after").first()
+            .and_then(|value| rate_limit::parse_retry_after(value));
+        if !host_throttle.add_response(&host, code, retry_after) || retries == MAX_THROTTLED_RETRIES {
+            return Ok(())
+        }
+        retries += 1;
+    }
+}
+
+// Returns the value of each header line with the given name, ignoring case
+pub fn header_values(headers: &[String], name: &str) -> Vec<String>
+{
+    headers.iter()
+        .filter_map(|header| {
+            let separator = header.find(':')?;
+            if header[..separator].trim().eq_ignore_ascii_case(name) {
+                Some(header[separator+1..].trim().to_string())
+            }
+            else {
+                None
+            }
+        })
+        .collect()
+}
+
+// Returns the header lines of the last response, including the status line
+pub fn captured_headers(easy: &Easy2<Collector>) -> Vec<String>
+{
+    easy.get_ref().headers.clone()
+}
+
+// Returns the version from the last status line of the headers, which is
+// for the final response, e.g. "HTTP/1.1" from "HTTP/1.1 200 OK"
+pub fn http_version(headers: &[String]) -> String
 {
-    easy.get_mut().clear_buffer();
-    easy.perform()
+    headers.iter().rev()
+        .find(|header| header.starts_with("HTTP/"))
+        .and_then(|status_line| status_line.split_whitespace().next())
+        .unwrap_or("")
+        .to_string()
 }
 
 // Get the current content of the given easy and return it as a string
@@ -279,6 +1191,42 @@ fn fabricate_request_response(url: String, is_directory: bool, is_listable: bool
         is_listable: is_listable,
         redirect_url: String::from(""),
         found_from_listable: true,
-        parent_depth: 0
+        parent_depth: 0,
+        ..Default::default()
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_expand_placeholders() {
+        assert_eq!(super::expand_placeholders("X-Test: {word}@{host}", "admin", "example.com"),
+            "X-Test: admin@example.com");
+        let expanded = super::expand_placeholders("X-Id: {rand}-{rand}", "admin", "example.com");
+        let (first, second) = expanded["X-Id: ".len()..].split_once('-').unwrap();
+        assert_eq!(first.len(), 16);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn check_bypass_urls() {
+        assert_eq!(super::bypass_urls("http://example.com/files/admin"), vec![
+            "http://example.com/files/%2e/admin", "http://example.com/files//admin",
+            "http://example.com/files/admin;/", "http://example.com/files/ADMIN",
+            "http://example.com/files/admin%20"]);
+        assert_eq!(super::bypass_urls("http://example.com/123/")[..2],
+            ["http://example.com/%2e/123/", "http://example.com//123/"]);
+        assert_eq!(super::bypass_urls("http://example.com/123/").len(), 4);
+        assert!(super::bypass_urls("http://example.com/").is_empty());
+    }
+
+    #[test]
+    fn check_cors_finding() {
+        assert_eq!(super::cors_origins("https://example.com:8443/api")[1], "https://example.com.dirble.example");
+        assert_eq!(super::cors_finding("null", "null", false), Some(String::from("null")));
+        assert_eq!(super::cors_finding("https://dirble.example", "https://dirble.example", true),
+            Some(String::from("https://dirble.example with credentials")));
+        assert_eq!(super::cors_finding("https://dirble.example", "*", true), Some(String::from("* with credentials")));
+        assert_eq!(super::cors_finding("https://dirble.example", "*", false), None);
+        assert_eq!(super::cors_finding("https://dirble.example", "https://example.com", true), None);
+    }
+}
@@ -21,53 +21,217 @@ use std::{
     time::Duration,
 };
 extern crate curl;
+use rand::{thread_rng, Rng};
 use crate::arg_parse;
+use crate::calibrate;
+use crate::discovered_hosts;
+use crate::output_bodies;
+use crate::output_format;
 use crate::request;
+use crate::rules;
+use crate::tui;
+use crate::url_parse;
 use crate::wordlist;
 
 pub fn thread_spawn(tx: mpsc::Sender<request::RequestResponse>, 
-    uri_gen: wordlist::UriGenerator, global_opts: Arc<arg_parse::GlobalOpts>) {
+    mut uri_gen: wordlist::UriGenerator, global_opts: Arc<arg_parse::GlobalOpts>) {
 
     let hostname = uri_gen.hostname.clone();
 
     if global_opts.verbose {
-        println!("Scanning {}", hostname);
+        tui::message(format!("Scanning {}", hostname));
     }
 
-    let mut easy = request::generate_easy(global_opts.clone());
+    let mut easy = global_opts.easy_pool.take(&hostname, global_opts.clone());
+
+    // The first thread to scan each host records its info for the report
+    let base_url = url_parse::base_url(&hostname);
+    if global_opts.scanned_hosts.claim(&base_url) {
+        if let Some(info) = request::host_info(&mut easy, &base_url) {
+            global_opts.scanned_hosts.record(&base_url, info);
+        }
+    }
 
     let mut consecutive_errors = 0;
     let parent_depth = uri_gen.parent_depth;
 
+    // Bodies are searched for other hosts under the targets' domains when collecting them
+    let hostname_pattern = if global_opts.discovered_hosts_file.is_some() || global_opts.auto_add_in_scope {
+        discovered_hosts::hostname_pattern(&discovered_hosts::target_domains(&global_opts))
+    }
+    else { None };
+
+    // Get the codes given by a missing path for each of the comparison verbs
+    let verb_baseline = if global_opts.verbs.is_empty() { Vec::new() }
+        else { request::verb_baseline(&mut easy, &hostname, &global_opts.verbs,
+            &global_opts) };
+
+    let placeholders = request::has_placeholders(&global_opts);
+
+    // Learn how the directory responds to paths which don't exist, so that
+    // results which respond the same way can be left out
+    let mut calibration = if global_opts.calibrate {
+        let calibration = calibrate::NotFound::measure(&mut easy, &uri_gen, global_opts.similarity);
+        if calibration.is_none() {
+            tui::message(format!("Couldn't get a consistent response for random paths in {}, \
+                results won't be calibrated", hostname));
+        }
+        calibration
+    }
+    else { None };
+
+    // Calibrated directories are recalibrated if their behaviour changes
+    let mut drift = if global_opts.calibrate { Some(calibrate::DriftMonitor::new()) } else { None };
+
     // For each item in the wordlist, call the request function on it
     // Then if there is a response send it to main
-    for uri in uri_gen {
+    while let Some((uri, word)) = uri_gen.next_with_word() {
+        if let Some(scope) = &global_opts.scope {
+            if !scope.contains(&uri) { continue }
+        }
+
+        // Fill in the placeholders in the user's headers for this request
+        if placeholders {
+            request::set_placeholder_headers(&mut easy, &global_opts, &word, &uri);
+        }
+        // Use a new address for each request when spoofing random addresses
+        else if global_opts.spoof_ip.as_deref() == Some("random") {
+            request::set_headers(&mut easy, &global_opts);
+        }
+
         let mut response = request::make_request(&mut easy, uri.clone());
 
+        // Retry with a new bearer token if the current one has expired
+        if response.code == 401 && request::refresh_bearer_token(&mut easy, &global_opts) {
+            response = request::make_request(&mut easy, uri.clone());
+        }
+        if let Some(bearer_token) = &global_opts.bearer_token {
+            if response.code != 401 && response.code != 0 {
+                bearer_token.accepted(&uri);
+            }
+        }
+
         let code = response.code.clone();
 
+        if let Some((start, end)) = global_opts.flag_time {
+            response.time_flagged = code != 0 && (start..=end).contains(&response.response_time);
+        }
+
+        // Search the body for keywords while it is still held by the easy
+        if !global_opts.keywords.is_empty() && code != 0 {
+            response.keywords = request::match_keywords(&easy, &global_opts.keywords);
+        }
+
+        // Checked now as comparing verbs makes more requests with the easy
+        let filtered = request::response_filtered(&easy, &global_opts);
+        let rule_action = rules::matching_action(&global_opts.rules, &easy, &response);
+        let excluded = calibration.as_ref().is_some_and(|calibration| calibration.matches(&response))
+            || global_opts.exclude_like.matches(&response, global_opts.similarity)
+            || rule_action == Some(rules::RuleAction::NotFound);
+        let skip_recursion = rule_action == Some(rules::RuleAction::NoRecurse);
+
+        // Server errors often give away the framework in debug or default error pages
+        if code >= 500 {
+            response.framework = request::error_framework(&easy);
+        }
+
+        // API error bodies often point at the correct endpoint or its documentation
+        if global_opts.api_mode && code >= 400 {
+            response.endpoint_hints = request::endpoint_hints(&easy);
+        }
+
+        if let Some(pattern) = &hostname_pattern {
+            response.hostnames = request::body_hostnames(&easy, pattern);
+        }
+
+        if global_opts.capture_headers && code != 0 {
+            response.headers = request::captured_headers(&easy);
+        }
+
+        // Keep the body to save if the response is reported, as comparing
+        // verbs makes more requests with the easy
+        // Directories have the body of their listing saved instead
+        let body = if global_opts.save_bodies.is_some() && code != 0 && !response.is_directory {
+            Some(easy.get_ref().contents.clone())
+        }
+        else { None };
+
+        // Request the url with the other verbs to see if they behave differently
+        if !verb_baseline.is_empty() && code != 0 {
+            response.verb_codes = request::compare_verbs(&mut easy, &uri, code,
+                &verb_baseline, &global_opts);
+        }
+
+        // Ask the urls which are found which methods and origins they allow
+        if code != 0 && !filtered && !excluded && (response.is_directory || reported(&global_opts, &response)) {
+            if global_opts.allowed_methods {
+                response.allowed_methods = request::allowed_methods(&mut easy, &uri, &global_opts);
+            }
+            if global_opts.cors {
+                response.cors = request::cors_misconfigurations(&mut easy, &uri, &global_opts);
+            }
+        }
+
+        // Try to get around the access rules of forbidden urls
+        if global_opts.bypass_403 && code == 403 && !filtered && !excluded {
+            response.bypasses = request::check_bypasses(&mut easy, &uri);
+        }
+
+        let mut result = None;
+
         // If the url is a directory, then check if it's listable
         // This may also scrape listable directories if the parameter is set
         // Then return each discovered item to the main thread
-        if response.is_directory {
+        if response.is_directory && !excluded {
             let mut response_list = request::listable_check(&mut easy, response.url, 
-                global_opts.disable_recursion, global_opts.scrape_listable);
+                &global_opts);
 
-            let mut original_response = response_list.remove(0);
-            original_response.found_from_listable = false;
-            original_response.parent_depth = parent_depth;
-            send_response(&tx, &global_opts, original_response);
+            // A directory with the same listing as a random one isn't scanned
+            // Nothing is returned for a directory whose listing matched a not-found rule
+            if !response_list.is_empty()
+                && calibration.as_ref().is_none_or(|calibration| !calibration.matches(&response_list[0])) {
+                let mut original_response = response_list.remove(0);
+                original_response.found_from_listable = false;
+                original_response.parent_depth = parent_depth;
+                original_response.skip_recursion |= skip_recursion;
+                original_response.keywords = response.keywords;
+                send_response(&tx, &global_opts, original_response);
 
-            for mut scraped_response in response_list {
-                scraped_response.parent_depth = parent_depth;
-                send_response(&tx, &global_opts, scraped_response);
+                for mut scraped_response in response_list {
+                    scraped_response.parent_depth = parent_depth;
+                    send_response(&tx, &global_opts, scraped_response);
+                }
             }
 
         } 
-        // If it isn't a directory then just send the response to the main thread
-        else {
+        // If it isn't a directory then the response is sent to the main thread
+        else if !filtered && !excluded && reported(&global_opts, &response) {
             response.parent_depth = parent_depth;
-            send_response(&tx, &global_opts, response); 
+            result = Some((response, body));
+        }
+
+        // Results are held back while the hit rate is watched
+        let ready = match drift.as_mut() {
+            Some(drift) => drift.add(result),
+            None => result.into_iter().collect()
+        };
+        for (response, body) in ready {
+            send_result(&tx, &global_opts, response, body);
+        }
+
+        if drift.as_ref().is_some_and(|drift| drift.drifted()) {
+            tui::message(format!("Nearly every recent request in {} was reported, recalibrating", hostname));
+            let new_calibration = calibrate::NotFound::measure(&mut easy, &uri_gen, global_opts.similarity);
+            if new_calibration.is_none() {
+                tui::message(format!("Couldn't get a consistent response for random paths in {}, \
+                    keeping the previous calibration", hostname));
+            }
+            let dropped = drift.as_mut().unwrap().recalibrate(new_calibration.as_ref());
+            if dropped > 0 {
+                tui::message(format!("Dropped {} results from {} which match its new response for missing paths",
+                    dropped, hostname));
+            }
+            calibration = new_calibration.or(calibration);
         }
 
         // Detect consecutive errors and stop the thread if the count is exceeded
@@ -75,9 +239,7 @@ pub fn thread_spawn(tx: mpsc::Sender<request::RequestResponse>,
             if code == 0 {
                 consecutive_errors += 1;
                 if consecutive_errors >= global_opts.max_errors {
-                    println!("Thread scanning {} stopping due to multiple consecutive errors received", hostname);
-
-                    tx.send(generate_end()).unwrap();
+                    tui::message(format!("Thread scanning {} stopping due to multiple consecutive errors received", hostname));
                     break;
                 }
             }
@@ -86,50 +248,123 @@ pub fn thread_spawn(tx: mpsc::Sender<request::RequestResponse>,
             }
         }
 
-        // Sleep if throttle is set
-        if global_opts.throttle != 0 {
-            thread::sleep(Duration::from_millis(global_opts.throttle as u64));
+        pause(&global_opts);
+    }
+
+    if let Some(drift) = drift.as_mut() {
+        for (response, body) in drift.flush() {
+            send_result(&tx, &global_opts, response, body);
         }
     }
 
     if global_opts.verbose {
-        println!("Finished scanning {}", hostname);
+        tui::message(format!("Finished scanning {}", hostname));
     }
 
+    global_opts.easy_pool.put(&hostname, easy);
+
     // Send a message to the main thread so it knows the thread is done
-    tx.send(generate_end()).unwrap();
+    tx.send(generate_end(hostname)).unwrap();
 }
 
-// Sends the given RequestResponse to the main thread
-// dependent on whitelist/blacklist settings and response code
+// Waits between requests if throttle or jitter is set
+pub fn pause(global_opts: &arg_parse::GlobalOpts) {
+    // Sleep if throttle is set
+    if global_opts.throttle != 0 {
+        thread::sleep(Duration::from_millis(global_opts.throttle as u64));
+    }
+
+    // Sleep for a random time if jitter is set, so requests aren't sent
+    // at a fixed interval
+    if global_opts.jitter != 0 {
+        thread::sleep(Duration::from_millis(thread_rng().gen_range(0..=global_opts.jitter) as u64));
+    }
+}
+
+// Sends the given RequestResponse to the main thread if it's reported
 fn send_response(tx: &mpsc::Sender<request::RequestResponse>, 
     global_opts: &arg_parse::GlobalOpts, response: request::RequestResponse) {
 
-    if response.is_directory {
+    if reported(global_opts, &response) {
         tx.send(response).unwrap();
-        return
     }
+}
 
-    let contains_code = global_opts.code_list.contains(&response.code);
+// Sends a reported file to the main thread, saving its body if requested
+fn send_result(tx: &mpsc::Sender<request::RequestResponse>,
+    global_opts: &arg_parse::GlobalOpts, response: request::RequestResponse, body: Option<Vec<u8>>) {
 
-    if (!global_opts.whitelist && !contains_code) ||
-            (global_opts.whitelist && contains_code)
-    {
-        tx.send(response).unwrap();
+    if let (Some(directory), Some(body)) = (&global_opts.save_bodies, body) {
+        output_bodies::save(directory, &response, &body);
     }
-
+    tx.send(response).unwrap();
 }
 
+// Checks whether the response should be reported
+// dependent on whitelist/blacklist settings, response code, size, type and time
+pub fn reported(global_opts: &arg_parse::GlobalOpts, response: &request::RequestResponse) -> bool {
+
+    // Filtered sizes are checked before anything else so that directories
+    // with a filtered size aren't scanned either
+    let in_sizes = |sizes: &[(usize, usize)]| sizes.iter()
+        .any(|(start, end)| (*start..=*end).contains(&response.content_len));
+    if in_sizes(&global_opts.filter_sizes) {
+        return false
+    }
+
+    // Directories, urls which respond differently to other verbs and
+    // forbidden urls which were bypassed are always sent
+    if response.is_directory || !response.verb_codes.is_empty() || !response.bypasses.is_empty() {
+        return true
+    }
+
+    if !global_opts.match_sizes.is_empty() && !in_sizes(&global_opts.match_sizes) {
+        return false
+    }
+
+    let in_time = |range: &(u64, u64)| (range.0..=range.1).contains(&response.response_time);
+    if global_opts.filter_time.as_ref().is_some_and(in_time)
+        || global_opts.match_time.as_ref().is_some_and(|range| !in_time(range)) {
+        return false
+    }
+
+    let in_types = |types: &[String]| types.iter()
+        .any(|media_type| output_format::media_type_matches(media_type, &response.content_type));
+    if in_types(&global_opts.filter_content_types)
+        || (!global_opts.match_content_types.is_empty() && !in_types(&global_opts.match_content_types)) {
+        return false
+    }
+
+    // Redirects to other hosts can be left out or reported on their own
+    let off_host = output_format::redirect_type(response)
+        .is_some_and(|redirect_type| redirect_type != "internal");
+    match global_opts.external_redirects {
+        arg_parse::RedirectFilter::Hide if off_host => return false,
+        arg_parse::RedirectFilter::Only if !off_host => return false,
+        _ => {}
+    }
+
+    // Hosts given their own codes are matched with or without the port
+    let host = url_parse::host(&response.url).to_lowercase();
+    let authority = url_parse::authority(&response.url).to_lowercase();
+    let code_filter = global_opts.host_code_filters.iter()
+        .find(|(filter_host, _)| *filter_host == host || *filter_host == authority)
+        .map_or(&global_opts.code_filter, |(_, code_filter)| code_filter);
+
+    code_filter.shows(response.code)
+}
 
-fn generate_end() -> request::RequestResponse {
+// The end message carries the url of the directory the thread was scanning
+pub fn generate_end(hostname: String) -> request::RequestResponse {
     request::RequestResponse {
         url: String::from("END"),
         code: 0,
         content_len: 0,
         is_directory:false,
         is_listable: false,
-        redirect_url: String::from(""),
+        redirect_url: hostname,
         found_from_listable: false,
-        parent_depth: 0
+        parent_depth: 0,
+        ..Default::default()
     }
 }
\ No newline at end of file
@@ -0,0 +1,142 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, process::exit};
+use curl::easy::Easy2;
+use regex::Regex;
+use serde::Deserialize;
+use crate::arg_parse;
+use crate::request::{Collector, RequestResponse};
+
+// A rule as it's written in the rules file, e.g.
+// {"code": "403", "size": "1234", "action": "not-found"}
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleEntry {
+    code: Option<String>,
+    size: Option<String>,
+    url: Option<String>,
+    body: Option<String>,
+    header: Option<String>,
+    action: RuleAction
+}
+
+// What happens to a response which matches a rule
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleAction {
+    // Treated as if the path doesn't exist, so it's neither reported nor
+    // scanned
+    NotFound,
+    // Reported, but not scanned if it's a directory
+    NoRecurse
+}
+
+// Conditions a response must meet every one of for the rule to apply, the
+// body and header patterns are matched against the body and each header line
+pub struct Rule {
+    codes: Vec<(u32, u32)>,
+    sizes: Vec<(usize, usize)>,
+    url: Option<Regex>,
+    body: Option<Regex>,
+    header: Option<Regex>,
+    action: RuleAction
+}
+
+impl Rule {
+    fn matches(&self, response: &RequestResponse, body: &str, headers: &[String]) -> bool {
+        let in_ranges = |ranges: &[(u32, u32)], value: u32| ranges.is_empty()
+            || ranges.iter().any(|(start, end)| (*start..=*end).contains(&value));
+        in_ranges(&self.codes, response.code)
+            && (self.sizes.is_empty() || self.sizes.iter().any(|(start, end)| (*start..=*end).contains(&response.content_len)))
+            && self.url.as_ref().is_none_or(|url| url.is_match(&response.url))
+            && self.body.as_ref().is_none_or(|pattern| pattern.is_match(body))
+            && self.header.as_ref().is_none_or(|pattern| headers.iter().any(|header| pattern.is_match(header)))
+    }
+}
+
+// Reads the rules from a JSON file holding a list of them, exiting if the
+// file can't be read or contains an invalid rule
+pub fn from_file(filename: &str) -> Vec<Rule> {
+    let contents = match fs::read_to_string(filename) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Couldn't read rules file {}: {}", filename, e);
+            exit(2);
+        }
+    };
+
+    match parse_rules(&contents) {
+        Ok(rules) => rules,
+        Err(e) => {
+            println!("Invalid rules file {}: {}", filename, e);
+            exit(2);
+        }
+    }
+}
+
+fn parse_rules(contents: &str) -> Result<Vec<Rule>, String> {
+    let entries: Vec<RuleEntry> = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    entries.into_iter()
+        .map(|entry| Ok(Rule {
+            codes: entry.code.map_or(Ok(Vec::new()), |codes| codes.split(',').map(arg_parse::parse_code_range).collect())?,
+            sizes: entry.size.map_or(Ok(Vec::new()), |sizes| sizes.split(',')
+                .map(|size| arg_parse::parse_range(size).ok_or(format!("{} isn't a size or a range of sizes", size)))
+                .collect())?,
+            url: entry.url.as_deref().map(rule_regex).transpose()?,
+            body: entry.body.as_deref().map(rule_regex).transpose()?,
+            header: entry.header.as_deref().map(rule_regex).transpose()?,
+            action: entry.action
+        }))
+        .collect()
+}
+
+fn rule_regex(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("invalid regular expression {}: {}", pattern, e))
+}
+
+// Returns the action of the first rule the last response matches
+pub fn matching_action(rules: &[Rule], easy: &Easy2<Collector>, response: &RequestResponse) -> Option<RuleAction> {
+    if rules.is_empty() || response.code == 0 { return None }
+
+    let body = String::from_utf8_lossy(&easy.get_ref().contents);
+    rules.iter()
+        .find(|rule| rule.matches(response, &body, &easy.get_ref().headers))
+        .map(|rule| rule.action)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::request::RequestResponse;
+
+    #[test]
+    fn check_rules() {
+        let rules = super::parse_rules(r#"[
+            {"code": "403", "size": "1234", "action": "not-found"},
+            {"code": "200-299", "body": "Access Denied", "action": "no-recurse"}
+        ]"#).unwrap();
+        let response = |code, content_len| RequestResponse { code, content_len, ..Default::default() };
+
+        assert!(rules[0].matches(&response(403, 1234), "", &[]));
+        assert!(!rules[0].matches(&response(403, 1235), "", &[]));
+        assert!(rules[1].matches(&response(200, 10), "<h1>Access Denied</h1>", &[]));
+        assert!(!rules[1].matches(&response(200, 10), "<h1>Welcome</h1>", &[]));
+
+        assert!(super::parse_rules(r#"[{"code": "4xx", "action": "not-found"}]"#).is_err());
+        assert!(super::parse_rules(r#"[{"code": "404", "action": "ignore"}]"#).is_err());
+    }
+}
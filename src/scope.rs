@@ -0,0 +1,177 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{fs, process::exit};
+use regex::Regex;
+use serde::Deserialize;
+use crate::url_parse;
+
+// The parts of a Burp Suite project options file which hold the target scope
+#[derive(Deserialize)]
+struct BurpOptions {
+    target: BurpTarget
+}
+
+#[derive(Deserialize)]
+struct BurpTarget {
+    scope: BurpScope
+}
+
+#[derive(Deserialize)]
+struct BurpScope {
+    #[serde(default)]
+    include: Vec<BurpRule>,
+    #[serde(default)]
+    exclude: Vec<BurpRule>
+}
+
+// Rules are either a url prefix, or in advanced mode a set of regular
+// expressions for the host, port and file along with a protocol
+#[derive(Deserialize)]
+struct BurpRule {
+    #[serde(default = "enabled_default")]
+    enabled: bool,
+    prefix: Option<String>,
+    protocol: Option<String>,
+    host: Option<String>,
+    port: Option<String>,
+    file: Option<String>
+}
+
+fn enabled_default() -> bool { true }
+
+enum ScopeRule {
+    Prefix(String),
+    Advanced {
+        protocol: Option<String>,
+        host: Option<Regex>,
+        port: Option<Regex>,
+        file: Option<Regex>
+    }
+}
+
+impl ScopeRule {
+    fn matches(&self, url: &str) -> bool {
+        match self {
+            ScopeRule::Prefix(prefix) => url.starts_with(prefix.as_str()),
+            ScopeRule::Advanced { protocol, host, port, file } => {
+                protocol.as_ref().is_none_or(|protocol| *protocol == url_parse::scheme(url))
+                    && host.as_ref().is_none_or(|host| host.is_match(&url_parse::host(url)))
+                    && port.as_ref().is_none_or(|port| port.is_match(&url_parse::port(url).to_string()))
+                    && file.as_ref().is_none_or(|file| file.is_match(&url_parse::path(url)))
+            }
+        }
+    }
+}
+
+// A target scope, urls are in scope if they match an include rule and
+// don't match any exclude rules
+pub struct Scope {
+    include: Vec<ScopeRule>,
+    exclude: Vec<ScopeRule>
+}
+
+impl Scope {
+    // Reads the scope from a Burp Suite project options file, exiting if
+    // the file can't be read or contains an invalid rule
+    pub fn from_burp_file(filename: &str) -> Scope {
+        let contents = match fs::read_to_string(filename) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("Couldn't read scope file {}: {}", filename, e);
+                exit(2);
+            }
+        };
+
+        let options: BurpOptions = match serde_json::from_str(&contents) {
+            Ok(options) => options,
+            Err(e) => {
+                println!("{} isn't a Burp Suite project options file with a target scope: {}", filename, e);
+                exit(2);
+            }
+        };
+
+        Scope {
+            include: scope_rules(options.target.scope.include),
+            exclude: scope_rules(options.target.scope.exclude)
+        }
+    }
+
+    pub fn contains(&self, url: &str) -> bool {
+        self.include.iter().any(|rule| rule.matches(url))
+            && !self.exclude.iter().any(|rule| rule.matches(url))
+    }
+}
+
+// Compiles the enabled rules, an empty pattern or a protocol of "any"
+// matches everything
+fn scope_rules(rules: Vec<BurpRule>) -> Vec<ScopeRule> {
+    rules.into_iter()
+        .filter(|rule| rule.enabled)
+        .map(|rule| match rule.prefix {
+            Some(prefix) => ScopeRule::Prefix(prefix),
+            None => ScopeRule::Advanced {
+                protocol: rule.protocol
+                    .map(|protocol| protocol.to_lowercase())
+                    .filter(|protocol| protocol != "any"),
+                host: rule.host.and_then(|host| scope_regex(&host)),
+                port: rule.port.and_then(|port| scope_regex(&port)),
+                file: rule.file.and_then(|file| scope_regex(&file))
+            }
+        })
+        .collect()
+}
+
+fn scope_regex(pattern: &str) -> Option<Regex> {
+    if pattern.is_empty() { return None }
+
+    match Regex::new(pattern) {
+        Ok(regex) => Some(regex),
+        Err(e) => {
+            println!("Invalid regular expression {} in the scope file: {}", pattern, e);
+            exit(2);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_burp_scope() {
+        let options: super::BurpOptions = serde_json::from_str(r#"{"target": {"scope": {
+            "advanced_mode": true,
+            "include": [
+                {"enabled": true, "protocol": "any", "host": "^(.*\\.)?example\\.com$", "port": "^(80|443)$", "file": "^/.*"},
+                {"enabled": false, "protocol": "any", "host": "^other\\.com$"}
+            ],
+            "exclude": [
+                {"enabled": true, "protocol": "https", "host": "^example\\.com$", "file": "^/logout.*"}
+            ]
+        }}}"#).unwrap();
+        let scope = super::Scope {
+            include: super::scope_rules(options.target.scope.include),
+            exclude: super::scope_rules(options.target.scope.exclude)
+        };
+
+        assert!(scope.contains("https://example.com/admin/"));
+        assert!(scope.contains("http://www.example.com/"));
+        assert!(scope.contains("http://example.com/logout"));
+        assert!(!scope.contains("https://example.com/logout"));
+        assert!(!scope.contains("http://example.com:8080/"));
+        assert!(!scope.contains("http://other.com/"));
+    }
+}
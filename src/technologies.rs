@@ -0,0 +1,237 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    process::exit,
+    sync::{Arc, mpsc::{self, Receiver, Sender}},
+    thread
+};
+use curl::easy::Easy2;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use crate::arg_parse::GlobalOpts;
+use crate::request::{self, Collector};
+use crate::update;
+use crate::url_parse;
+use crate::wordlist::{self, Wordlist};
+
+// A technology as it's written in the mapping file, e.g.
+// {"name": "PHP", "header": "^X-Powered-By: PHP", "extensions": ".php,.phtml", "wordlist": "php.txt"}
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TechnologyEntry {
+    name: String,
+    header: Option<String>,
+    body: Option<String>,
+    extensions: Option<String>,
+    wordlist: Option<String>
+}
+
+// A technology fingerprinted from the response to a host's root, and the
+// extensions and wordlist its hosts are scanned with on top of the usual ones
+// Header patterns are matched against each header line ignoring case, as
+// header names are lowercase over HTTP/2
+pub struct Technology {
+    pub name: String,
+    header: Option<Regex>,
+    body: Option<Regex>,
+    pub extensions: Vec<String>,
+    words: Option<Vec<String>>,
+    pub wordlist_file: Option<String>
+}
+
+impl Technology {
+    fn matches(&self, body: &str, headers: &[String]) -> bool {
+        self.header.as_ref().is_none_or(|pattern| headers.iter().any(|header| pattern.is_match(header)))
+            && self.body.as_ref().is_none_or(|pattern| pattern.is_match(body))
+    }
+}
+
+// Reads the technologies from a JSON file holding a list of them, exiting if
+// the file can't be read or contains an invalid entry
+pub fn from_file(filename: &str) -> Vec<Technology> {
+    let contents = match fs::read_to_string(filename) {
+        Ok(contents) => contents,
+        Err(e) => {
+            println!("Couldn't read technology mapping file {}: {}", filename, e);
+            exit(2);
+        }
+    };
+
+    match parse_technologies(&contents) {
+        Ok(technologies) => technologies,
+        Err(e) => {
+            println!("Invalid technology mapping file {}: {}", filename, e);
+            exit(2);
+        }
+    }
+}
+
+fn parse_technologies(contents: &str) -> Result<Vec<Technology>, String> {
+    let entries: Vec<TechnologyEntry> = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    entries.into_iter()
+        .map(|entry| {
+            if entry.header.is_none() && entry.body.is_none() {
+                return Err(format!("{} needs a header or body pattern to be detected by", entry.name))
+            }
+            Ok(Technology {
+                header: entry.header.as_deref()
+                    .map(|pattern| RegexBuilder::new(pattern).case_insensitive(true).build()
+                        .map_err(|e| format!("invalid regular expression {}: {}", pattern, e)))
+                    .transpose()?,
+                body: entry.body.as_deref()
+                    .map(|pattern| Regex::new(pattern).map_err(|e| format!("invalid regular expression {}: {}", pattern, e)))
+                    .transpose()?,
+                extensions: entry.extensions.map_or(Vec::new(), |extensions| extensions.split(',')
+                    .map(|extension| String::from(extension.trim()))
+                    .filter(|extension| !extension.is_empty())
+                    .collect()),
+                words: entry.wordlist.as_ref().map(|filename| {
+                    let mut words = wordlist::lines_from_file(update::resolve_data_file(filename));
+                    words.sort();
+                    words.dedup();
+                    words
+                }),
+                wordlist_file: entry.wordlist.map(|filename| update::resolve_data_file(&filename)),
+                name: entry.name
+            })
+        })
+        .collect()
+}
+
+// Returns the wordlist of each technology without the words which are in
+// the main wordlist, so that hosts it matches don't have them requested twice
+pub fn extra_wordlists(technologies: &[Technology], wordlist: &Wordlist) -> Vec<Option<Wordlist>> {
+    technologies.iter()
+        .map(|technology| {
+            let words: Vec<String> = technology.words.as_ref()?.iter()
+                .filter(|word| !wordlist.contains(word))
+                .cloned()
+                .collect();
+            if words.is_empty() { None } else { Some(Wordlist::Loaded(Arc::new(words))) }
+        })
+        .collect()
+}
+
+// Returns the index of each technology the last response matches
+fn matching(technologies: &[Technology], easy: &Easy2<Collector>) -> Vec<usize> {
+    let body = String::from_utf8_lossy(&easy.get_ref().contents);
+    technologies.iter().enumerate()
+        .filter(|(_, technology)| technology.matches(&body, &easy.get_ref().headers))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+// Fingerprints hosts one at a time on its own thread, so that the main loop
+// isn't held up by their requests
+pub struct Fingerprinter {
+    urls: Sender<String>,
+    results: Receiver<(String, Vec<usize>)>,
+    pending: usize
+}
+
+impl Fingerprinter {
+    pub fn start(global_opts: Arc<GlobalOpts>) -> Fingerprinter {
+        let (urls, url_rx) = mpsc::channel::<String>();
+        let (result_tx, results) = mpsc::channel();
+        thread::spawn(move || {
+            let mut easy = request::generate_easy(global_opts.clone());
+            for url in url_rx {
+                let matched = fingerprint(&mut easy, &url, &global_opts);
+                if result_tx.send((url, matched)).is_err() { return }
+            }
+        });
+        Fingerprinter { urls, results, pending: 0 }
+    }
+
+    pub fn add(&mut self, url: &str) {
+        self.urls.send(String::from(url)).unwrap();
+        self.pending += 1;
+    }
+
+    // Returns a host which has been fingerprinted, with the index of each
+    // technology it matched
+    pub fn try_recv(&mut self) -> Option<(String, Vec<usize>)> {
+        let result = self.results.try_recv().ok()?;
+        self.pending -= 1;
+        Some(result)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.pending == 0
+    }
+}
+
+// Requests the url and returns the technologies the response matches
+// The details of a host's root are kept for the report, so that it isn't
+// requested again once the scan starts
+fn fingerprint(easy: &mut Easy2<Collector>, url: &str, global_opts: &GlobalOpts) -> Vec<usize> {
+    let base_url = url_parse::base_url(url);
+    let is_root = url.trim_end_matches('/') == base_url;
+    easy.certinfo(is_root && url.starts_with("https://")).unwrap();
+    let code = request::make_request(easy, String::from(url)).code;
+    easy.certinfo(false).unwrap();
+    if code == 0 { return Vec::new() }
+
+    if is_root && global_opts.scanned_hosts.claim(&base_url) {
+        global_opts.scanned_hosts.record(&base_url, request::last_host_info(easy, &base_url));
+    }
+    matching(&global_opts.technologies, easy)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    #[test]
+    fn check_technologies() {
+        let technologies = super::parse_technologies(r#"[
+            {"name": "PHP", "header": "^X-Powered-By: PHP", "extensions": ".php, .phtml"},
+            {"name": "ASP.NET", "header": "^X-AspNet-Version:", "body": "__VIEWSTATE", "extensions": ".aspx"}
+        ]"#).unwrap();
+        assert_eq!(technologies[0].extensions, vec![".php", ".phtml"]);
+
+        let headers = vec![String::from("x-powered-by: PHP/8.2"), String::from("X-AspNet-Version: 4.0")];
+        assert!(technologies[0].matches("", &headers));
+        assert!(!technologies[1].matches("<html></html>", &headers));
+        assert!(technologies[1].matches("<input name=\"__VIEWSTATE\">", &headers));
+
+        assert!(super::parse_technologies(r#"[{"name": "PHP", "extensions": ".php"}]"#).is_err());
+        assert!(super::parse_technologies(r#"[{"name": "PHP", "header": "(", "extensions": ".php"}]"#).is_err());
+    }
+
+    #[test]
+    fn check_extra_wordlists() {
+        let mut technologies = super::parse_technologies(r#"[
+            {"name": "PHP", "header": "^X-Powered-By: PHP"},
+            {"name": "WordPress", "body": "wp-content"},
+            {"name": "Drupal", "body": "Drupal"}
+        ]"#).unwrap();
+        technologies[1].words = Some(vec!["admin".into(), "wp-admin".into(), "wp-login.php".into()]);
+        technologies[2].words = Some(vec!["admin".into()]);
+
+        let wordlist = super::Wordlist::Loaded(Arc::new(vec!["admin".into(), "index.php".into(), "login".into()]));
+        let extra = super::extra_wordlists(&technologies, &wordlist);
+        assert!(extra[0].is_none());
+        match &extra[1] {
+            Some(super::Wordlist::Loaded(words)) => assert_eq!(**words, vec!["wp-admin", "wp-login.php"]),
+            _ => panic!("WordPress should have the words missing from the main wordlist")
+        }
+        assert!(extra[2].is_none(), "A wordlist with nothing new shouldn't be scanned");
+    }
+}
@@ -0,0 +1,268 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}},
+    time::{Duration, Instant}
+};
+use ratatui::{
+    DefaultTerminal,
+    crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Row, Table}
+};
+use crate::arg_parse::GlobalOpts;
+use crate::output;
+use crate::request::RequestResponse;
+use crate::url_parse;
+
+// Time between redraws of the dashboard
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+// Number of findings kept for the scrolling findings pane
+const MAX_FINDINGS: usize = 1000;
+
+// Number of status messages kept for the messages pane
+const MAX_MESSAGES: usize = 100;
+
+// Set while the dashboard owns the terminal, status messages from the
+// worker threads are then queued for it instead of being printed over it
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static MESSAGES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+// Prints a status message, or queues it for the messages pane if the
+// dashboard is being shown
+pub fn message(text: String) {
+    if ACTIVE.load(Ordering::SeqCst) {
+        let mut messages = MESSAGES.lock().unwrap();
+        messages.push_back(text);
+        if messages.len() > MAX_MESSAGES {
+            messages.pop_front();
+        }
+    }
+    else {
+        println!("{}", text);
+    }
+}
+
+// Progress of the scan against a single host
+struct HostProgress {
+    base_url: String,
+    generators_queued: usize,
+    generators_finished: usize,
+    found: usize,
+    errors: usize
+}
+
+// Interactive dashboard which replaces the line by line output
+pub struct Dashboard {
+    terminal: DefaultTerminal,
+    global_opts: Arc<GlobalOpts>,
+    hosts: Vec<HostProgress>,
+    findings: VecDeque<String>,
+    messages: VecDeque<String>,
+    found: usize,
+    errors: usize,
+    threads_in_use: u32,
+    started: Instant,
+    last_draw: Instant
+}
+
+impl Dashboard {
+    // Switches the terminal to the alternate screen and creates the dashboard
+    pub fn new(global_opts: Arc<GlobalOpts>) -> Dashboard {
+        let mut hosts: Vec<HostProgress> = Vec::new();
+        for hostname in &global_opts.hostnames {
+            let base_url = url_parse::base_url(hostname);
+            if hosts.iter().all(|host| host.base_url != base_url) {
+                hosts.push(HostProgress {
+                    base_url,
+                    generators_queued: 0,
+                    generators_finished: 0,
+                    found: 0,
+                    errors: 0
+                });
+            }
+        }
+
+        ACTIVE.store(true, Ordering::SeqCst);
+        Dashboard {
+            terminal: ratatui::init(),
+            global_opts,
+            hosts,
+            findings: VecDeque::new(),
+            messages: VecDeque::new(),
+            found: 0,
+            errors: 0,
+            threads_in_use: 0,
+            started: Instant::now(),
+            last_draw: Instant::now() - REDRAW_INTERVAL
+        }
+    }
+
+    // Hosts added during the scan get a row when their first generators are queued
+    pub fn generators_queued(&mut self, url: &str, count: usize) {
+        if self.host(url).is_none() {
+            self.hosts.push(HostProgress {
+                base_url: url_parse::base_url(url),
+                generators_queued: 0,
+                generators_finished: 0,
+                found: 0,
+                errors: 0
+            });
+        }
+        if let Some(host) = self.host(url) {
+            host.generators_queued += count;
+        }
+    }
+
+    pub fn generator_finished(&mut self, url: &str) {
+        if let Some(host) = self.host(url) {
+            host.generators_finished += 1;
+        }
+    }
+
+    // Adds a response to the findings pane, curl errors are only counted
+    pub fn add_response(&mut self, response: &RequestResponse) {
+        let is_error = response.code == 0 && !response.found_from_listable;
+        if let Some(host) = self.host(&response.url) {
+            if is_error { host.errors += 1 } else { host.found += 1 }
+        }
+
+        if is_error {
+            self.errors += 1;
+            return
+        }
+
+        self.found += 1;
+        if let Some(line) = output::print_response(response, self.global_opts.clone(), false, false, false) {
+            self.findings.push_back(line);
+            if self.findings.len() > MAX_FINDINGS {
+                self.findings.pop_front();
+            }
+        }
+    }
+
+    // Redraws the dashboard if enough time has passed since the last draw
+    // Returns false if the user has asked to stop the scan
+    pub fn update(&mut self, threads_in_use: u32) -> bool {
+        self.threads_in_use = threads_in_use;
+
+        self.messages.extend(MESSAGES.lock().unwrap().drain(..));
+        while self.messages.len() > MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+
+        while event::poll(Duration::from_secs(0)).unwrap_or(false) {
+            if let Ok(Event::Key(key)) = event::read() {
+                let ctrl_c = key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+                if key.kind == KeyEventKind::Press && (key.code == KeyCode::Char('q') || ctrl_c) {
+                    return false
+                }
+            }
+        }
+
+        if self.last_draw.elapsed() >= REDRAW_INTERVAL {
+            self.draw();
+            self.last_draw = Instant::now();
+        }
+        true
+    }
+
+    // Restores the terminal so that the final report can be printed
+    pub fn close(mut self) {
+        self.draw();
+        ratatui::restore();
+        ACTIVE.store(false, Ordering::SeqCst);
+    }
+
+    fn host(&mut self, url: &str) -> Option<&mut HostProgress> {
+        let base_url = url_parse::base_url(url);
+        self.hosts.iter_mut().find(|host| host.base_url == base_url)
+    }
+
+    fn draw(&mut self) {
+        let summary = format!("Elapsed: {}s   Found: {}   Errors: {}   Press q to stop",
+            self.started.elapsed().as_secs(), self.found, self.errors);
+        let max_threads = self.global_opts.max_threads.max(1);
+        let thread_ratio = (f64::from(self.threads_in_use) / f64::from(max_threads)).min(1.0);
+        let thread_label = format!("{}/{} threads", self.threads_in_use, max_threads);
+
+        let host_rows: Vec<Row> = self.hosts.iter()
+            .map(|host| {
+                let percent = (host.generators_finished * 100).checked_div(host.generators_queued).unwrap_or(0);
+                Row::new(vec![
+                    host.base_url.clone(),
+                    format!("{}/{} ({}%)", host.generators_finished, host.generators_queued, percent),
+                    host.found.to_string(),
+                    host.errors.to_string()
+                ])
+            })
+            .collect();
+        let host_count = host_rows.len() as u16;
+
+        let findings = &self.findings;
+        let messages = &self.messages;
+
+        self.terminal.draw(|frame| {
+            let [header_area, threads_area, hosts_area, findings_area, messages_area] = Layout::vertical([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length((host_count + 3).min(12)),
+                Constraint::Min(3),
+                Constraint::Length(6)
+            ]).areas(frame.area());
+
+            frame.render_widget(Paragraph::new(Line::from(summary)), header_area);
+
+            frame.render_widget(Gauge::default()
+                .gauge_style(Style::default().fg(Color::Cyan))
+                .ratio(thread_ratio)
+                .label(thread_label), threads_area);
+
+            frame.render_widget(Table::new(host_rows, [
+                    Constraint::Fill(1),
+                    Constraint::Length(22),
+                    Constraint::Length(8),
+                    Constraint::Length(8)
+                ])
+                .header(Row::new(vec!["Host", "Generators", "Found", "Errors"])
+                    .style(Style::default().fg(Color::Yellow)))
+                .block(Block::default().borders(Borders::ALL).title("Hosts")), hosts_area);
+
+            // Show the most recent findings which fit in the pane
+            let visible = findings_area.height.saturating_sub(2) as usize;
+            let items: Vec<ListItem> = findings.iter()
+                .skip(findings.len().saturating_sub(visible))
+                .map(|line| ListItem::new(line.clone()))
+                .collect();
+            frame.render_widget(List::new(items)
+                .block(Block::default().borders(Borders::ALL).title("Findings")), findings_area);
+
+            let visible = messages_area.height.saturating_sub(2) as usize;
+            let items: Vec<ListItem> = messages.iter()
+                .skip(messages.len().saturating_sub(visible))
+                .map(|line| ListItem::new(line.clone()))
+                .collect();
+            frame.render_widget(List::new(items)
+                .block(Block::default().borders(Borders::ALL).title("Messages")), messages_area);
+        }).unwrap();
+    }
+}
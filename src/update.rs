@@ -0,0 +1,195 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::{
+    env,
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+    time::Duration
+};
+use curl::easy::Easy;
+use crate::hash::sha256;
+
+// Location the curated wordlists are downloaded from
+pub const DEFAULT_UPDATE_URL: &str = "https://raw.githubusercontent.com/nccgroup/dirble/master/wordlists/";
+
+// Index of the files to download, in the format written by sha256sum
+const INDEX_NAME: &str = "SHA256SUMS";
+
+// Returns the directory updated data files are kept in
+pub fn config_dir() -> Option<PathBuf>
+{
+    let base = match env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => match env::var_os("APPDATA") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config")
+        }
+    };
+    Some(base.join("dirble"))
+}
+
+// Returns the downloaded copy of a data file if the file doesn't exist at the
+// given path, so that updated wordlists can be used by name from anywhere
+pub fn resolve_data_file(filename: &str) -> String
+{
+    if Path::new(filename).exists() || Path::new(filename).is_absolute() {
+        return String::from(filename)
+    }
+
+    match config_dir().map(|dir| dir.join(filename)) {
+        Some(path) if path.is_file() => path.to_string_lossy().to_string(),
+        _ => String::from(filename)
+    }
+}
+
+// Downloads each file listed in the index at the update url into the config
+// directory, checking each against the hash in the index
+// The index comes from the same place, so this only guards against corruption
+pub fn run_update(base_url: &str)
+{
+    let base_url = if base_url.ends_with('/') { String::from(base_url) } else { format!("{}/", base_url) };
+    let config_dir = match config_dir() {
+        Some(dir) => dir,
+        None => {
+            println!("Couldn't find a config directory to store updates in, set XDG_CONFIG_HOME or HOME");
+            exit(2);
+        }
+    };
+
+    let index = match fetch(&(base_url.clone() + INDEX_NAME)) {
+        Ok(index) => String::from_utf8_lossy(&index).to_string(),
+        Err(e) => {
+            println!("Couldn't download the update index from {}{}: {}", base_url, INDEX_NAME, e);
+            exit(2);
+        }
+    };
+
+    let mut failed = false;
+    for (hash, name) in index.lines().filter_map(parse_index_line) {
+        let destination = config_dir.join(&name);
+        if fs::read(&destination).map(|contents| sha256(&contents) == hash).unwrap_or(false) {
+            println!("{} is up to date", name);
+            continue
+        }
+
+        let contents = match fetch(&(base_url.clone() + &name)) {
+            Ok(contents) => contents,
+            Err(e) => {
+                println!("Couldn't download {}: {}", name, e);
+                failed = true;
+                continue
+            }
+        };
+        if sha256(&contents) != hash {
+            println!("{} doesn't match the hash in the index, skipping", name);
+            failed = true;
+            continue
+        }
+
+        // Write to a temporary file first so an interrupted update doesn't
+        // leave a partial file behind
+        let temporary = destination.with_extension("part");
+        let written = destination.parent().map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&temporary, &contents))
+            .and_then(|_| fs::rename(&temporary, &destination));
+        match written {
+            Ok(_) => println!("Updated {}", destination.display()),
+            Err(e) => {
+                println!("Couldn't write {}: {}", destination.display(), e);
+                failed = true;
+            }
+        }
+    }
+
+    if failed { exit(2); }
+}
+
+// Parses a "<sha256>  <path>" line of the index, ignoring paths which could
+// escape the config directory
+fn parse_index_line(line: &str) -> Option<(String, String)>
+{
+    let mut parts = line.trim().splitn(2, char::is_whitespace);
+    let hash = parts.next()?.to_lowercase();
+    let name = parts.next()?.trim().trim_start_matches('*');
+
+    let safe = !name.is_empty() && !name.starts_with('/') && !name.contains('\\')
+        && name.split('/').all(|part| !part.is_empty() && part != "." && part != "..");
+    if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) && safe {
+        Some((hash, String::from(name)))
+    }
+    else {
+        None
+    }
+}
+
+fn fetch(url: &str) -> Result<Vec<u8>, String>
+{
+    let mut easy = Easy::new();
+    let mut contents = Vec::new();
+    easy.url(url).map_err(|e| e.to_string())?;
+    easy.follow_location(true).unwrap();
+    easy.timeout(Duration::from_secs(60)).unwrap();
+
+    {
+        let mut transfer = easy.transfer();
+        transfer.write_function(|data| {
+            contents.extend_from_slice(data);
+            Ok(data.len())
+        }).unwrap();
+        transfer.perform().map_err(|e| e.to_string())?;
+    }
+
+    match easy.response_code().unwrap() {
+        200 => Ok(contents),
+        code => Err(format!("the server responded with {}", code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_index_parsing() {
+        let hash = "a".repeat(64);
+        assert_eq!(super::parse_index_line(&format!("{}  web.lst", hash)),
+            Some((hash.clone(), String::from("web.lst"))));
+        assert_eq!(super::parse_index_line(&format!("{} *packs/cms.lst", hash)),
+            Some((hash.clone(), String::from("packs/cms.lst"))));
+        assert_eq!(super::parse_index_line(&format!("{}  ../escape.lst", hash)), None);
+        assert_eq!(super::parse_index_line(&format!("{}  /etc/passwd", hash)), None);
+        assert_eq!(super::parse_index_line("abc  web.lst"), None);
+    }
+
+    #[test]
+    fn check_index_matches_wordlists() {
+        let wordlists = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("wordlists");
+        let index = std::fs::read_to_string(wordlists.join(super::INDEX_NAME)).unwrap();
+        let mut names = Vec::new();
+        for (hash, name) in index.lines().filter_map(super::parse_index_line) {
+            assert_eq!(super::sha256(&std::fs::read(wordlists.join(&name)).unwrap()), hash, "{}", name);
+            names.push(name);
+        }
+
+        let mut wordlist_names: Vec<String> = std::fs::read_dir(&wordlists).unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".lst"))
+            .collect();
+        wordlist_names.sort();
+        assert_eq!(names, wordlist_names);
+    }
+}
@@ -0,0 +1,172 @@
+// This file is part of Dirble - https://www.github.com/nccgroup/dirble
+// Copyright (C) 2019 Izzy Whistlecroft <Izzy(dot)Whistlecroft(at)nccgroup(dot)com>
+// Released as open source by NCC Group Plc - https://www.nccgroup.com/
+//
+// Dirble is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Dirble is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, mpsc};
+use curl::easy::Easy2;
+use rand::{thread_rng, Rng, distributions::Alphanumeric};
+use crate::arg_parse::GlobalOpts;
+use crate::request::{self, Collector, RequestResponse};
+use crate::request_thread;
+use crate::tui;
+use crate::url_parse;
+use crate::wordlist::UriGenerator;
+
+// Lengths of the random names used to find the default virtual host's response
+const PROBE_LENGTHS: [usize; 2] = [12, 20];
+
+// The response for names the server has no virtual host for, which is
+// measured with random names of two lengths so that a default page which
+// includes the Host header in its body still matches
+struct DefaultVhost {
+    code: u32,
+    size: i64,
+    name_len: i64,
+    // Change in size for each extra character in the name
+    size_per_char: i64
+}
+
+impl DefaultVhost {
+    fn measure(easy: &mut Easy2<Collector>, url: &str, global_opts: &GlobalOpts) -> Option<DefaultVhost> {
+        let probes: Vec<(String, RequestResponse)> = PROBE_LENGTHS.iter()
+            .map(|length| {
+                let name = vhost_name(&random_label(*length), global_opts);
+                let response = request_vhost(easy, url, &name, global_opts);
+                (name, response)
+            })
+            .collect();
+        let (first_name, first) = &probes[0];
+        let (second_name, second) = &probes[1];
+        if first.code == 0 || first.code != second.code { return None }
+
+        let name_difference = second_name.len() as i64 - first_name.len() as i64;
+        Some(DefaultVhost {
+            code: first.code,
+            size: first.content_len as i64,
+            name_len: first_name.len() as i64,
+            size_per_char: (second.content_len as i64 - first.content_len as i64) / name_difference
+        })
+    }
+
+    fn matches(&self, name: &str, response: &RequestResponse) -> bool {
+        let expected_size = self.size + self.size_per_char * (name.len() as i64 - self.name_len);
+        response.code == self.code && response.content_len as i64 == expected_size
+    }
+}
+
+// Requests the target's url with each word in the Host header, sending the
+// responses which differ from the default virtual host's to the main thread
+pub fn thread_spawn(tx: mpsc::Sender<RequestResponse>, mut uri_gen: UriGenerator, global_opts: Arc<GlobalOpts>) {
+    let hostname = uri_gen.hostname.clone();
+    let url = hostname.clone() + "/";
+
+    if global_opts.verbose {
+        tui::message(format!("Scanning {} for virtual hosts", url));
+    }
+
+    let mut easy = global_opts.easy_pool.take(&url, global_opts.clone());
+    let default_vhost = DefaultVhost::measure(&mut easy, &url, &global_opts);
+    if default_vhost.is_none() {
+        tui::message(format!("Couldn't get a consistent response for unknown virtual hosts on {}, \
+            every virtual host will be reported", url));
+    }
+
+    let mut consecutive_errors = 0;
+    while let Some(word) = uri_gen.next_word() {
+        let name = vhost_name(&word, &global_opts);
+        let mut response = request_vhost(&mut easy, &url, &name, &global_opts);
+
+        // Retry with a new bearer token if the current one has expired
+        if response.code == 401 && request::refresh_bearer_token(&mut easy, &global_opts) {
+            response = request_vhost(&mut easy, &url, &name, &global_opts);
+        }
+        let code = response.code;
+
+        let default = default_vhost.as_ref().is_some_and(|default_vhost| default_vhost.matches(&name, &response));
+        if code != 0 && !default && !request::response_filtered(&easy, &global_opts) {
+            response.url = vhost_url(&url, &name);
+            response.parent_depth = uri_gen.parent_depth;
+            if request_thread::reported(&global_opts, &response) {
+                tx.send(response).unwrap();
+            }
+        }
+
+        // Detect consecutive errors and stop the thread if the count is exceeded
+        if global_opts.max_errors != 0 {
+            if code == 0 {
+                consecutive_errors += 1;
+                if consecutive_errors >= global_opts.max_errors {
+                    tui::message(format!("Thread scanning {} stopping due to multiple consecutive errors received", hostname));
+                    break;
+                }
+            }
+            else {
+                consecutive_errors = 0;
+            }
+        }
+
+        request_thread::pause(&global_opts);
+    }
+
+    if global_opts.verbose {
+        tui::message(format!("Finished scanning {}", hostname));
+    }
+
+    global_opts.easy_pool.put(&url, easy);
+    tx.send(request_thread::generate_end(hostname)).unwrap();
+}
+
+// Requests the url with the name in the Host header
+fn request_vhost(easy: &mut Easy2<Collector>, url: &str, name: &str, global_opts: &GlobalOpts) -> RequestResponse {
+    easy.get_mut().host_header = Some(String::from(name));
+    easy.get_mut().placeholder_values = (String::from(name), String::from(name));
+    request::set_headers(easy, global_opts);
+    request::make_request(easy, String::from(url))
+}
+
+// Generates the name to send for a word, under the domain if one was given
+fn vhost_name(word: &str, global_opts: &GlobalOpts) -> String {
+    match &global_opts.vhost_domain {
+        Some(domain) if !domain.is_empty() => format!("{}.{}", word, domain),
+        _ => String::from(word)
+    }
+}
+
+// Generates the url a virtual host is reported as, which is the target's url
+// with the name in place of its host
+fn vhost_url(url: &str, name: &str) -> String {
+    let authority = url_parse::authority(url);
+    let port = &authority[url_parse::host(url).len()..];
+    format!("{}://{}{}{}", url_parse::scheme(url), name, port, url_parse::path(url))
+}
+
+fn random_label(length: usize) -> String {
+    thread_rng().sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect::<String>()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn check_vhost_url() {
+        assert_eq!(super::vhost_url("http://10.0.0.1/", "admin.example.com"), "http://admin.example.com/");
+        assert_eq!(super::vhost_url("https://10.0.0.1:8443/app/", "dev"), "https://dev:8443/app/");
+        assert_eq!(super::vhost_url("http://[::1]:8080/", "dev"), "http://dev:8080/");
+    }
+}
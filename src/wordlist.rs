@@ -16,11 +16,13 @@
 // along with Dirble.  If not, see <https://www.gnu.org/licenses/>.
 
 use std::{
+    collections::HashSet,
     process::exit,
     sync::Arc,
     fs::File,
-    io::prelude::*
+    io::{self, prelude::*, BufReader, SeekFrom}
 };
+use memmap2::Mmap;
 use percent_encoding::{utf8_percent_encode, DEFAULT_ENCODE_SET};
 use chardet::{detect, charset2encoding};
 use encoding::{
@@ -29,13 +31,218 @@ use encoding::{
 };
 
 
+// Tokens in a word which are replaced with the generator's extension or
+// prefix, rather than the extension being appended or the prefix prepended
+// The extension is substituted without its leading dot, so that words such
+// as backup.%EXT%.old can be used
+const EXTENSION_TOKEN: &str = "%EXT%";
+const PREFIX_TOKEN: &str = "%PREFIX%";
+
+// Where the generators get their words from
+#[derive(Clone)]
+pub enum Wordlist {
+    // Every word held in memory
+    Loaded(Arc<Vec<String>>),
+    // The wordlist files mapped into memory, with only the position of
+    // each word held on the heap
+    Mapped(Arc<MappedWordlist>),
+    // The wordlist files, read a line at a time by each generator so that
+    // very large wordlists don't have to fit in memory. Each generator only
+    // reads its own share of the bytes of each file
+    Streamed(Arc<Vec<String>>),
+    // Every combination of a set of characters, generated as it's used
+    Brute(Arc<BruteForce>)
+}
+
+impl Wordlist {
+    // Checks each of the files can be opened before the scan starts, as
+    // they aren't read until a generator needs them
+    pub fn streamed(filenames: Vec<String>) -> Wordlist {
+        for filename in &filenames {
+            if let Err(error) = File::open(filename) {
+                println!("Opening file \"{}\" failed: {}", filename, error);
+                exit(2);
+            }
+        }
+        Wordlist::Streamed(Arc::new(filenames))
+    }
+
+    // Checks whether the word is in the wordlist, loaded wordlists are
+    // expected to be sorted and streamed ones aren't read ahead of the scan
+    // so are never said to contain it
+    pub fn contains(&self, word: &str) -> bool {
+        match self {
+            Wordlist::Loaded(words) => words.binary_search_by(|line| line.as_str().cmp(word)).is_ok(),
+            Wordlist::Mapped(mapped) => mapped.contains(word),
+            Wordlist::Streamed(_) => false,
+            Wordlist::Brute(brute_force) => brute_force.contains(word)
+        }
+    }
+}
+
+// Where a line of a mapped wordlist is
+struct MappedLine {
+    start: usize,
+    len: u32,
+    file: u32
+}
+
+// A wordlist whose files are memory mapped rather than read, so that very
+// large wordlists start quickly and the threads share a single copy
+// The lines are sorted and deduplicated like a loaded wordlist, and are
+// decoded as UTF-8 when a generator reaches them
+pub struct MappedWordlist {
+    maps: Vec<Mmap>,
+    lines: Vec<MappedLine>
+}
+
+impl MappedWordlist {
+    pub fn open(filenames: &[String]) -> MappedWordlist {
+        let maps: Vec<Mmap> = filenames.iter()
+            .map(|filename| {
+                let file = File::open(filename)
+                    .unwrap_or_else(|error| { println!("Opening file \"{}\" failed: {}", filename, error); exit(2); });
+                // Safety: the map is only read, it's up to the user not to
+                // change the wordlist files during the scan
+                unsafe { Mmap::map(&file) }
+                    .unwrap_or_else(|error| { println!("Mapping file \"{}\" failed: {}", filename, error); exit(2); })
+            })
+            .collect();
+
+        let mut lines = Vec::new();
+        for (file, map) in maps.iter().enumerate() {
+            let mut start = 0;
+            for line in map.split(|byte| *byte == b'\n') {
+                let len = line.strip_suffix(b"\r").unwrap_or(line).len();
+                lines.push(MappedLine { start, len: len as u32, file: file as u32 });
+                start += line.len() + 1;
+            }
+            // A final newline doesn't start another line
+            if map.ends_with(b"\n") || map.is_empty() {
+                lines.pop();
+            }
+        }
+
+        let mut wordlist = MappedWordlist { maps, lines: Vec::new() };
+        lines.sort_unstable_by(|first, second| wordlist.bytes(first).cmp(wordlist.bytes(second)));
+        lines.dedup_by(|first, second| wordlist.bytes(first) == wordlist.bytes(second));
+        wordlist.lines = lines;
+        wordlist
+    }
+
+    fn bytes(&self, line: &MappedLine) -> &[u8] {
+        &self.maps[line.file as usize][line.start..line.start + line.len as usize]
+    }
+
+    // Returns the word at the index, or None past the end of the wordlist
+    pub fn word(&self, index: usize) -> Option<String> {
+        self.lines.get(index).map(|line| String::from_utf8_lossy(self.bytes(line)).to_string())
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.lines.binary_search_by(|line| self.bytes(line).cmp(word.as_bytes())).is_ok()
+    }
+}
+
+// Generates every combination of the characters up to the maximum length,
+// shortest first, so that the word at any index can be worked out without
+// generating the ones before it
+#[derive(Clone)]
+pub struct BruteForce {
+    pub charset: Vec<char>,
+    pub max_length: u32
+}
+
+impl BruteForce {
+    // Parses a brute force mode given as charset:length, where the charset
+    // can contain ranges such as a-z
+    // The length is checked so that every index fits in a usize
+    pub fn parse(value: &str) -> Result<BruteForce, String> {
+        let (charset, length) = value.rsplit_once(':')
+            .ok_or_else(|| String::from("The brute force mode must be given as charset:length"))?;
+
+        let chars: Vec<char> = charset.chars().collect();
+        let mut expanded: Vec<char> = Vec::new();
+        let mut position = 0;
+        while position < chars.len() {
+            // A - at the start or end of the charset is taken literally
+            if position + 2 < chars.len() && chars[position + 1] == '-' {
+                let (start, end) = (chars[position], chars[position + 2]);
+                if start > end {
+                    return Err(format!("{}-{} isn't a valid range of characters", start, end))
+                }
+                expanded.extend(start..=end);
+                position += 3;
+            }
+            else {
+                expanded.push(chars[position]);
+                position += 1;
+            }
+        }
+        let mut seen = HashSet::new();
+        expanded.retain(|character| seen.insert(*character));
+        if expanded.is_empty() {
+            return Err(String::from("The brute force charset can't be empty"))
+        }
+
+        let brute_force = match length.parse::<u32>() {
+            Ok(max_length) if max_length > 0 => BruteForce { charset: expanded, max_length },
+            _ => return Err(String::from("The brute force length must be a positive integer"))
+        };
+        if brute_force.word_count().is_none() {
+            return Err(format!("Brute forcing {} characters up to length {} gives too many words",
+                brute_force.charset.len(), brute_force.max_length))
+        }
+        Ok(brute_force)
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        let length = word.chars().count();
+        length >= 1 && length <= self.max_length as usize
+            && word.chars().all(|character| self.charset.contains(&character))
+    }
+
+    // Returns the number of words generated, or None if it doesn't fit in a
+    // usize
+    pub fn word_count(&self) -> Option<usize> {
+        (1..=self.max_length).try_fold(0usize, |total, length| {
+            total.checked_add(self.charset.len().checked_pow(length)?)
+        })
+    }
+
+    // Returns the word at the index, or None past the last combination
+    pub fn word(&self, mut index: usize) -> Option<String> {
+        let base = self.charset.len();
+        for length in 1..=self.max_length {
+            let count = base.pow(length);
+            if index < count {
+                let mut word = vec![self.charset[0]; length as usize];
+                for character in word.iter_mut().rev() {
+                    *character = self.charset[index % base];
+                    index /= base;
+                }
+                return Some(word.into_iter().collect())
+            }
+            index -= count;
+        }
+        None
+    }
+}
+
 // Struct for a UriGenerator, it needs the hostname, the suffix to append, a wordlist and an index into that wordlist
 pub struct UriGenerator {
     pub hostname: String,
     prefix: String,
     suffix: String,
     current_index: usize,
-    wordlist: Arc<Vec<String>>,
+    wordlist: Wordlist,
+    // The file being read, the position of the next one in the list, and
+    // the position in the file and the end of the generator's share of it,
+    // only used when the wordlist is streamed
+    reader: Option<BufReader<File>>,
+    next_file: usize,
+    position: u64,
+    range_end: u64,
     step_size: usize,
     pub parent_depth: u32
 }
@@ -43,7 +250,7 @@ pub struct UriGenerator {
 // Generates a new UriGenerator given various options
 impl UriGenerator {
     pub fn new(mut hostname: String, prefix: String, suffix: String, 
-        wordlist: Arc<Vec<String>>, index: u32, step: u32, original_depth:u32) -> UriGenerator{
+        wordlist: Wordlist, index: u32, step: u32, original_depth:u32) -> UriGenerator{
         // Remove a trailing / characters from the url if there is one
         if hostname.ends_with("/") {
             hostname.pop();
@@ -55,37 +262,156 @@ impl UriGenerator {
             suffix: suffix,
             current_index: index as usize,
             wordlist: wordlist,
+            reader: None,
+            next_file: 0,
+            position: 0,
+            range_end: 0,
             step_size: step as usize,
             parent_depth: original_depth
         }
     }
-}
 
-// Defines iterating over a UriGenerator
-impl Iterator for UriGenerator {
-    type Item = (String);
-
-    fn next(&mut self) -> Option<Self::Item> {
-        
+    // Returns the next wordlist item on its own, for scans which use the
+    // words somewhere other than the path
+    pub fn next_word(&mut self) -> Option<String> {
         // If we're at the end of the wordlist then return None
-        if self.current_index >= self.wordlist.len() {
-            return None;
-        }
-        // Concatenate the hostname with the current wordlist item and the suffix, then url encode
-        let uri = self.hostname.clone() + "/" + &self.prefix + &self.wordlist[self.current_index].clone() + &self.suffix;
-        let uri = utf8_percent_encode(&uri, DEFAULT_ENCODE_SET).to_string();
+        let word = match &self.wordlist {
+            Wordlist::Loaded(wordlist) => wordlist.get(self.current_index)?.clone(),
+            Wordlist::Mapped(wordlist) => wordlist.word(self.current_index)?,
+            Wordlist::Brute(brute_force) => brute_force.word(self.current_index)?,
+            Wordlist::Streamed(filenames) => {
+                let filenames = filenames.clone();
+                return self.next_streamed_word(&filenames)
+            }
+        };
 
         // Maintain the index into the wordlist
         self.current_index += self.step_size;
+        Some(word)
+    }
+
+    // Reads the next line from the generator's share of the wordlist files,
+    // opening each file only once the previous one is finished
+    fn next_streamed_word(&mut self, filenames: &[String]) -> Option<String> {
+        let mut line = Vec::new();
+        loop {
+            if self.reader.is_none() {
+                let filename = filenames.get(self.next_file)?;
+                self.next_file += 1;
+                match self.open_range(filename) {
+                    Ok(reader) => self.reader = Some(reader),
+                    Err(error) => {
+                        println!("Opening file \"{}\" failed: {}", filename, error);
+                        continue
+                    }
+                }
+            }
+
+            // Lines which start past the end of the range belong to the next generator
+            if self.position >= self.range_end {
+                self.reader = None;
+                continue
+            }
+
+            line.clear();
+            match self.reader.as_mut().unwrap().read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => {
+                    self.reader = None;
+                    continue
+                },
+                Ok(length) => {
+                    self.position += length as u64;
+                    return Some(decode_line(&line))
+                }
+            }
+        }
+    }
+
+    // Opens a wordlist file at the start of the generator's share of it,
+    // which is moved on to the start of the next line. Each line is read by
+    // the generator whose share it starts in
+    fn open_range(&mut self, filename: &str) -> io::Result<BufReader<File>> {
+        let file = File::open(filename)?;
+        let length = file.metadata()?.len();
+        let start = length * self.current_index as u64 / self.step_size as u64;
+        self.range_end = length * (self.current_index as u64 + 1) / self.step_size as u64;
+
+        let mut reader = BufReader::new(file);
+        self.position = start;
+        if start > 0 {
+            reader.seek(SeekFrom::Start(start - 1))?;
+            let skipped = reader.read_until(b'\n', &mut Vec::new())?;
+            self.position = start - 1 + skipped as u64;
+        }
+        Ok(reader)
+    }
+
+    // Returns the next uri along with the wordlist item it was generated from
+    // Words with tokens are skipped by the generators with no extension or
+    // prefix to fill them in with
+    pub fn next_with_word(&mut self) -> Option<(String, String)> {
+        let word = loop {
+            let word = self.next_word()?;
+            if (self.suffix.is_empty() && word.contains(EXTENSION_TOKEN))
+                || (self.prefix.is_empty() && word.contains(PREFIX_TOKEN)) {
+                continue
+            }
+            break word
+        };
+        let uri = self.uri_for(&word);
+
         // Return the generated Uri
-        Some(uri)
+        Some((uri, word))
+    }
+
+    // Generates the uri the generator would request for a word
+    pub fn uri_for(&self, word: &str) -> String {
+        let mut path = String::from(word);
+        if path.contains(EXTENSION_TOKEN) {
+            path = path.replace(EXTENSION_TOKEN, self.suffix.strip_prefix('.').unwrap_or(&self.suffix));
+        }
+        else {
+            path += &self.suffix;
+        }
+        if path.contains(PREFIX_TOKEN) {
+            path = path.replace(PREFIX_TOKEN, &self.prefix);
+        }
+        else {
+            path = self.prefix.clone() + &path;
+        }
+
+        // Concatenate the hostname with the path, then url encode
+        let uri = self.hostname.clone() + "/" + &path;
+        utf8_percent_encode(&uri, DEFAULT_ENCODE_SET).to_string()
+    }
+
+    // Generates the uri for a word without the generator's extension, or
+    // None if it doesn't add one
+    pub fn uri_without_suffix(&self, word: &str) -> Option<String> {
+        if self.suffix.is_empty() { return None }
+        let uri = self.hostname.clone() + "/" + &self.prefix + word;
+        Some(utf8_percent_encode(&uri, DEFAULT_ENCODE_SET).to_string())
+    }
+}
+
+// Defines iterating over a UriGenerator
+impl Iterator for UriGenerator {
+    type Item = (String);
 
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_word().map(|(uri, _)| uri)
     }
 }
 
+
 // Function used to read in lines from the wordlist file
+// A filename of - reads the lines from stdin instead
 pub fn lines_from_file(filename: String) -> Vec<String>
 {
+    if filename == "-" {
+        return lines_from_stdin()
+    }
+
     let mut file = File::open(filename.clone())
         .unwrap_or_else(|error| { println!("Opening file \"{}\" failed: {}", filename, error); exit(2);});
     let mut reader: Vec<u8> = Vec::new();
@@ -114,4 +440,93 @@ pub fn lines_from_file(filename: String) -> Vec<String>
             panic!("Error detecting file encoding");
         }
     }
+}
+
+// Reads lines from stdin as they arrive, so that a generator's output can
+// be piped in without being held in memory twice to detect its encoding
+// Lines are expected to be UTF-8, anything else is replaced
+fn lines_from_stdin() -> Vec<String>
+{
+    io::stdin().lock().split(b'\n')
+        .map(|line| line.unwrap_or_else(|error| { println!("Reading from stdin failed: {}", error); exit(2); }))
+        .map(|line| decode_line(&line))
+        .collect()
+}
+
+// Decodes a line read without detecting its encoding, which is expected to
+// be UTF-8 with anything else replaced
+fn decode_line(line: &[u8]) -> String
+{
+    String::from_utf8_lossy(line).trim_end_matches(['\r', '\n']).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, env, sync::Arc};
+
+    #[test]
+    fn check_streamed_wordlist() {
+        let path = env::temp_dir().join(format!("dirble_wordlist_{}.txt", std::process::id()));
+        fs::write(&path, "admin\r\nlogin\nbackup\nconfig").unwrap();
+        let wordlist = super::Wordlist::Streamed(Arc::new(vec![path.to_string_lossy().to_string()]));
+
+        // Each generator only takes the lines which start in its share of the file
+        let words = |index, step| {
+            let mut uri_gen = super::UriGenerator::new("http://example.com".into(), String::new(),
+                String::new(), wordlist.clone(), index, step, 0);
+            std::iter::from_fn(move || uri_gen.next_word()).collect::<Vec<String>>()
+        };
+        assert_eq!(words(0, 2), vec!["admin", "login"]);
+        assert_eq!(words(1, 2), vec!["backup", "config"]);
+
+        // Every line is read exactly once, however the file is split
+        for step in 1..=30 {
+            let all_words: Vec<String> = (0..step).flat_map(|index| words(index, step)).collect();
+            assert_eq!(all_words, vec!["admin", "login", "backup", "config"]);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn check_mapped_wordlist() {
+        let path = env::temp_dir().join(format!("dirble_mapped_wordlist_{}.txt", std::process::id()));
+        fs::write(&path, "login\r\nadmin\n\nlogin\nbackup\n").unwrap();
+        let wordlist = super::MappedWordlist::open(&[path.to_string_lossy().to_string()]);
+        fs::remove_file(&path).unwrap();
+
+        // Sorted and deduplicated like a loaded wordlist
+        let words: Vec<String> = (0..).map_while(|index| wordlist.word(index)).collect();
+        assert_eq!(words, vec!["", "admin", "backup", "login"]);
+    }
+
+    #[test]
+    fn check_word_tokens() {
+        let wordlist = super::Wordlist::Loaded(Arc::new(vec![
+            "admin".into(), "backup.%EXT%.old".into(), "%PREFIX%-%EXT%.%EXT%".into()]));
+        let uris = |prefix: &str, suffix: &str| {
+            let uri_gen = super::UriGenerator::new("http://example.com/".into(), prefix.into(), suffix.into(),
+                wordlist.clone(), 0, 1, 0);
+            uri_gen.collect::<Vec<String>>()
+        };
+
+        assert_eq!(uris("", ""), vec!["http://example.com/admin"]);
+        assert_eq!(uris("", ".php"), vec!["http://example.com/admin.php", "http://example.com/backup.php.old"]);
+        assert_eq!(uris("old", ".zip"), vec!["http://example.com/oldadmin.zip", "http://example.com/oldbackup.zip.old",
+            "http://example.com/old-zip.zip"]);
+    }
+
+    #[test]
+    fn check_brute_force() {
+        let brute_force = super::BruteForce::parse("a-c-:2").unwrap();
+        assert_eq!(brute_force.charset, vec!['a', 'b', 'c', '-']);
+        assert_eq!(brute_force.word_count(), Some(20));
+        assert_eq!(brute_force.word(0), Some(String::from("a")));
+        assert_eq!(brute_force.word(4), Some(String::from("aa")));
+        assert_eq!(brute_force.word(19), Some(String::from("--")));
+        assert_eq!(brute_force.word(20), None);
+
+        assert!(super::BruteForce::parse("z-a:2").is_err());
+        assert!(super::BruteForce::parse("abc:0").is_err());
+        assert!(super::BruteForce::parse("a-z0-9:20").is_err());
+    }
 }
\ No newline at end of file